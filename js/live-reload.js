@@ -0,0 +1,77 @@
+/**
+ * Live reload for pages served by `serve`.
+ *
+ * serve adds this script to the end of every page it builds, prefixed with:
+ *   LIVE_RELOAD_BUILD - the number of the build the page came from
+ *
+ * The server sends a `status` event on /__livereload when the page connects
+ * and after every rebuild: { build, errors }, where errors is null or
+ * { title, messages }. Errors are shown in an overlay over the game; a
+ * successful build other than this page's reloads it. The overlay is made
+ * with DOM calls and element.style, which the page's Content-Security-Policy
+ * allows, rather than markup or a <style> block, which it would not.
+ */
+
+(function () {
+    const OVERLAY_ID = 'live-reload-overlay';
+
+    function showErrors(errors) {
+        hideErrors();
+        const overlay = document.createElement('div');
+        overlay.id = OVERLAY_ID;
+        Object.assign(overlay.style, {
+            position: 'fixed',
+            inset: '0',
+            zIndex: '2147483647',
+            overflow: 'auto',
+            padding: '24px',
+            background: 'rgba(20, 20, 20, 0.94)',
+            color: '#f5f5f5',
+            font: '14px/1.5 ui-monospace, Menlo, Consolas, monospace'
+        });
+
+        const title = document.createElement('h2');
+        title.textContent = errors.title;
+        Object.assign(title.style, { margin: '0 0 16px', color: '#ff6b6b', font: 'bold 18px sans-serif' });
+        overlay.appendChild(title);
+
+        errors.messages.forEach((message) => {
+            const entry = document.createElement('pre');
+            entry.textContent = message;
+            Object.assign(entry.style, {
+                margin: '0 0 12px',
+                padding: '8px 12px',
+                whiteSpace: 'pre-wrap',
+                borderLeft: '3px solid #ff6b6b',
+                background: 'rgba(255, 255, 255, 0.05)'
+            });
+            overlay.appendChild(entry);
+        });
+
+        const hint = document.createElement('p');
+        hint.textContent = 'Fix the files above and save; the page reloads when the build succeeds.';
+        hint.style.color = '#aaa';
+        overlay.appendChild(hint);
+
+        document.body.appendChild(overlay);
+    }
+
+    function hideErrors() {
+        const overlay = document.getElementById(OVERLAY_ID);
+        if (overlay) {
+            overlay.remove();
+        }
+    }
+
+    const events = new EventSource('/__livereload');
+    events.addEventListener('status', (event) => {
+        const status = JSON.parse(event.data);
+        if (status.errors) {
+            showErrors(status.errors);
+        } else if (status.build !== LIVE_RELOAD_BUILD) {
+            location.reload();
+        } else {
+            hideErrors();
+        }
+    });
+})();
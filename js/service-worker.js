@@ -0,0 +1,60 @@
+/**
+ * Service worker for the offline build (bundle --pwa).
+ *
+ * bundle writes this file as sw.js next to the page, prefixed with:
+ *   CACHE_PREFIX  - shared by every build of the game
+ *   CACHE_NAME    - CACHE_PREFIX plus the build's content hash
+ *   START_URL     - the page, relative to this script
+ *   PRECACHE_URLS - the page, manifest, icons and question images
+ *
+ * Everything is cached on install and served cache-first, so the quiz keeps
+ * working when a classroom tablet loses its connection. A new build has a new
+ * CACHE_NAME, which makes the browser install it as an update; activation
+ * then drops the caches of older builds.
+ */
+
+self.addEventListener('install', (event) => {
+    event.waitUntil(
+        caches.open(CACHE_NAME)
+            .then((cache) => cache.addAll(PRECACHE_URLS))
+            .then(() => self.skipWaiting())
+    );
+});
+
+self.addEventListener('activate', (event) => {
+    event.waitUntil(
+        caches.keys()
+            .then((names) => Promise.all(
+                names
+                    .filter((name) => name.startsWith(CACHE_PREFIX) && name !== CACHE_NAME)
+                    .map((name) => caches.delete(name))
+            ))
+            .then(() => self.clients.claim())
+    );
+});
+
+self.addEventListener('fetch', (event) => {
+    if (event.request.method !== 'GET') {
+        return;
+    }
+    event.respondWith(
+        caches.open(CACHE_NAME).then(async (cache) => {
+            const cached = await cache.match(event.request, { ignoreSearch: true });
+            if (cached) {
+                return cached;
+            }
+            try {
+                return await fetch(event.request);
+            } catch (error) {
+                // Offline: any navigation within the scope gets the game
+                if (event.request.mode === 'navigate') {
+                    const page = await cache.match(START_URL);
+                    if (page) {
+                        return page;
+                    }
+                }
+                throw error;
+            }
+        })
+    );
+});
@@ -3,6 +3,19 @@
  * Handles all user interface interactions and updates
  */
 
+/**
+ * English text for the keys below, for pages whose template doesn't embed
+ * window.STRINGS (e.g. a theme's own index.hbs).
+ */
+const DEFAULT_STRINGS = {
+    'game.counter': 'Question {current} of {total}',
+    'game.score': 'Score: {score}/{answered}',
+    'result.correct': 'Correct!',
+    'result.incorrect': 'Incorrect. The correct answer was: {answer}',
+    'finish.score-value': '{score} out of {total}',
+    'finish.percentage-value': '{percentage}%'
+};
+
 class UIController {
     constructor(gameEngine) {
         this.game = gameEngine;
@@ -10,6 +23,20 @@ class UIController {
         this.init();
     }
 
+    /**
+     * Text for `key` in the page language, from window.STRINGS (bundled from
+     * templates/strings/<lang>.json), with its {name} placeholders filled
+     * from `values`.
+     */
+    text(key, values = {}) {
+        const strings = window.STRINGS || {};
+        const template = strings[key] !== undefined ? strings[key] : DEFAULT_STRINGS[key];
+        return Object.entries(values).reduce(
+            (text, [name, value]) => text.split(`{${name}}`).join(String(value)),
+            template
+        );
+    }
+
     /**
      * Initialize UI elements and event listeners
      */
@@ -114,8 +141,11 @@ class UIController {
             if (window.EMBEDDED_QUESTION_SETS) {
                 this.questionSets = window.EMBEDDED_QUESTION_SETS;
                 
-                // Load the first available question set by default
-                const firstKey = Object.keys(this.questionSets)[0];
+                // Load the bundle's default set, or the first available one
+                const defaultKey = window.DEFAULT_QUESTION_SET_KEY;
+                const firstKey = (defaultKey && this.questionSets[defaultKey])
+                    ? defaultKey
+                    : Object.keys(this.questionSets)[0];
                 if (firstKey) {
                     const success = await this.game.loadQuestions(this.questionSets[firstKey]);
                     if (success) {
@@ -250,13 +280,13 @@ class UIController {
 
         // Update question counter and score
         if (this.elements.questionCounter) {
-            this.elements.questionCounter.textContent = 
-                `Question ${stats.answered + 1} of ${stats.total}`;
+            this.elements.questionCounter.textContent =
+                this.text('game.counter', { current: stats.answered + 1, total: stats.total });
         }
         
         if (this.elements.scoreDisplay) {
-            this.elements.scoreDisplay.textContent = 
-                `Score: ${stats.score}/${stats.answered}`;
+            this.elements.scoreDisplay.textContent =
+                this.text('game.score', { score: stats.score, answered: stats.answered });
         }
 
         // Display question
@@ -266,7 +296,7 @@ class UIController {
         
         // Display question image if available
         if (this.elements.questionImage && question.image1) {
-            this.elements.questionImage.src = question.image1;
+            this.elements.questionImage.src = this.imageUrl(question.image1);
             this.elements.questionImage.style.display = 'block';
         } else if (this.elements.questionImage) {
             this.elements.questionImage.style.display = 'none';
@@ -349,9 +379,9 @@ class UIController {
         }
         
         if (this.elements.resultText) {
-            this.elements.resultText.textContent = result.correct 
-                ? 'Correct!' 
-                : `Incorrect. The correct answer was: ${result.correctAnswerText}`;
+            this.elements.resultText.textContent = result.correct
+                ? this.text('result.correct')
+                : this.text('result.incorrect', { answer: result.correctAnswerText });
         }
         
         if (this.elements.explanationText) {
@@ -361,7 +391,7 @@ class UIController {
         // Display answer image if available
         const currentQuestion = this.game.getCurrentQuestion();
         if (this.elements.answerImage && currentQuestion && currentQuestion.image2) {
-            this.elements.answerImage.src = currentQuestion.image2;
+            this.elements.answerImage.src = this.imageUrl(currentQuestion.image2);
             this.elements.answerImage.style.display = 'block';
         } else if (this.elements.answerImage) {
             this.elements.answerImage.style.display = 'none';
@@ -392,11 +422,13 @@ class UIController {
         const stats = this.game.getStats();
         
         if (this.elements.finalScore) {
-            this.elements.finalScore.textContent = `${stats.score} out of ${stats.total}`;
+            this.elements.finalScore.textContent =
+                this.text('finish.score-value', { score: stats.score, total: stats.total });
         }
         
         if (this.elements.finalPercentage) {
-            this.elements.finalPercentage.textContent = `${stats.percentage}%`;
+            this.elements.finalPercentage.textContent =
+                this.text('finish.percentage-value', { percentage: stats.percentage });
         }
 
         this.showScreen('finish');
@@ -436,19 +468,56 @@ class UIController {
         }
     }
 
+    /**
+     * Resolve an image name from the question data. Bundles built with
+     * --inline-images or --optimize-images map each name to a data URI or an
+     * optimised file in window.IMAGE_ASSETS, optionally with a WebP version
+     * that is preferred where the browser can show it. Otherwise the name is
+     * a path relative to the page.
+     */
+    imageUrl(name) {
+        const asset = window.IMAGE_ASSETS && window.IMAGE_ASSETS[name];
+        if (!asset) {
+            return name;
+        }
+        if (asset.webp && this.supportsWebp()) {
+            return asset.webp;
+        }
+        return asset.src;
+    }
+
+    /**
+     * Whether the browser can display WebP images (checked once)
+     */
+    supportsWebp() {
+        if (this.webpSupported === undefined) {
+            const canvas = document.createElement('canvas');
+            canvas.width = canvas.height = 1;
+            this.webpSupported = canvas.toDataURL('image/webp').indexOf('data:image/webp') === 0;
+        }
+        return this.webpSupported;
+    }
+
     /**
      * Show error message
      */
     showError(message) {
-        // Simple error display - could be enhanced with a proper error screen
+        // Simple error display - could be enhanced with a proper error screen.
+        // Built from elements rather than markup: the message may contain
+        // arbitrary text, and the page's Content-Security-Policy blocks
+        // inline onclick handlers.
         if (this.elements.loadingScreen) {
-            this.elements.loadingScreen.innerHTML = `
-                <div class="error">
-                    <h2>Error</h2>
-                    <p>${message}</p>
-                    <button onclick="location.reload()">Retry</button>
-                </div>
-            `;
+            const error = document.createElement('div');
+            error.className = 'error';
+            const heading = document.createElement('h2');
+            heading.textContent = 'Error';
+            const text = document.createElement('p');
+            text.textContent = message;
+            const retry = document.createElement('button');
+            retry.textContent = 'Retry';
+            retry.addEventListener('click', () => location.reload());
+            error.append(heading, text, retry);
+            this.elements.loadingScreen.replaceChildren(error);
         }
     }
 }
\ No newline at end of file
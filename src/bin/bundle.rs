@@ -1,169 +1,231 @@
-use anyhow::{Context, Result};
-use chrono::Local;
+use anyhow::Result;
+use clap::Parser;
 use colored::*;
-use handlebars::Handlebars;
-use serde_json::{json, Value};
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
-use std::process::Command;
-use walkdir::WalkDir;
+use just_learn_just_build_tools::bundle;
+use just_learn_just_build_tools::config::{Config, ConfigArgs};
+use std::path::PathBuf;
 
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("{} {}", "Error:".red().bold(), e);
-        std::process::exit(1);
-    }
-}
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Bundles the game into a single HTML file", long_about = None)]
+struct Args {
+    /// Directory containing the questions*.json files [default: data]
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
 
-fn run() -> Result<()> {
-    println!("{}", "📦 Just Learn Just Game - Build Tool".blue().bold());
-    println!("{}", "=".repeat(50).dimmed());
-
-    // Step 1: Load all question sets
-    println!("{}", "Loading question sets...".yellow());
-    let question_sets = load_question_sets()?;
-    println!("  {} Loaded {} question sets", "→".green(), question_sets.len());
-
-    // Step 2: Load CSS and JS
-    println!("\n{}", "Loading assets...".yellow());
-    let css_content = fs::read_to_string("css/style.css")
-        .context("Failed to read css/style.css")?;
-    println!("  {} Loaded CSS ({}kb)", "→".green(), css_content.len() / 1024);
-    
-    let js_engine = fs::read_to_string("js/game-engine.js")
-        .context("Failed to read js/game-engine.js")?;
-    let js_ui = fs::read_to_string("js/ui.js")
-        .context("Failed to read js/ui.js")?;
-    let js_content = format!("{}\n\n{}", js_engine, js_ui);
-    println!("  {} Loaded JS ({}kb)", "→".green(), js_content.len() / 1024);
-
-    // Step 3: Generate build metadata
-    println!("\n{}", "Generating build metadata...".yellow());
-    let build_info = generate_build_info();
-    println!("  {} Version: {}", "→".green(), build_info.version);
-    println!("  {} Timestamp: {}", "→".green(), build_info.timestamp);
-
-    // Step 4: Prepare template data
-    let template_data = json!({
-        "question_sets_json": serde_json::to_string_pretty(&question_sets)?,
-        "default_question_set": question_sets.get("questions")
-            .map(|v| serde_json::to_string_pretty(v).unwrap_or_default())
-            .or_else(|| {
-                // If "questions" doesn't exist, use first available set
-                question_sets.iter().next()
-                    .map(|(_, v)| serde_json::to_string_pretty(v).unwrap_or_default())
-            }),
-        "css_content": css_content,
-        "js_content": js_content,
-        "build_timestamp": build_info.timestamp,
-        "build_timestamp_unix": build_info.timestamp_unix,
-        "version": build_info.version,
-    });
+    /// Handlebars template for the page [default: templates/index.hbs]
+    #[arg(long, value_name = "PATH")]
+    template: Option<PathBuf>,
+
+    /// Theme directory overriding the template, partials, strings and
+    /// styles; see the README
+    #[arg(long, value_name = "DIR")]
+    theme: Option<PathBuf>,
+
+    /// Language of the page's text: which templates/strings/<lang>.json
+    /// the `t` helper reads [default: en]
+    #[arg(long, value_name = "LANG")]
+    lang: Option<String>,
+
+    /// Stylesheet to embed (repeatable, concatenated in order) [default: css/style.css]
+    #[arg(long, value_name = "PATH")]
+    css: Vec<PathBuf>,
+
+    /// Script to embed (repeatable, concatenated in order) [default: js/game-engine.js js/ui.js]
+    #[arg(long, value_name = "PATH")]
+    js: Vec<PathBuf>,
+
+    /// Where to write the bundled page [default: index.html]
+    #[arg(short, long, value_name = "PATH")]
+    out: Option<PathBuf>,
+
+    /// Directory the question images are read from [default: .]
+    #[arg(long, value_name = "DIR")]
+    asset_root: Option<PathBuf>,
+
+    /// Only bundle question sets whose key matches (repeatable; `*` wildcards
+    /// allowed, e.g. questions_ross_sea_*)
+    #[arg(long, value_name = "KEY")]
+    include: Vec<String>,
+
+    /// Leave out question sets whose key matches (repeatable; `*` wildcards allowed)
+    #[arg(long, value_name = "KEY")]
+    exclude: Vec<String>,
+
+    /// Question set selected when the page opens. Defaults to `questions` if
+    /// present, otherwise the first set
+    #[arg(long, value_name = "KEY")]
+    default_set: Option<String>,
+
+    /// Embed every image referenced by image1/image2 as a data URI, so the
+    /// page works without the PNG files next to it
+    #[arg(long, overrides_with = "no_inline_images")]
+    inline_images: bool,
+
+    /// Link the image files instead of inlining them, overriding game.toml
+    #[arg(long, overrides_with = "inline_images")]
+    no_inline_images: bool,
+
+    /// Scale down and re-encode referenced images. Unless they are inlined,
+    /// the results are written to --image-out-dir next to the page
+    #[arg(long, overrides_with = "no_optimize_images")]
+    optimize_images: bool,
+
+    /// Use the images as they are, overriding game.toml
+    #[arg(long, overrides_with = "optimize_images")]
+    no_optimize_images: bool,
+
+    /// Longest side, in pixels, of optimised images [default: 640]
+    #[arg(long, value_name = "PX")]
+    max_image_dimension: Option<u32>,
+
+    /// Palette size of optimised PNGs (2-256); 0 keeps full colour [default: 256]
+    #[arg(long, value_name = "N")]
+    image_colors: Option<u16>,
+
+    /// Also produce WebP versions of optimised images; the PNG stays as fallback
+    #[arg(long, overrides_with = "no_webp")]
+    webp: bool,
+
+    /// Don't produce WebP versions, overriding game.toml
+    #[arg(long, overrides_with = "webp")]
+    no_webp: bool,
+
+    /// Quality of the WebP versions (0-100) [default: 80]
+    #[arg(long, value_name = "Q")]
+    webp_quality: Option<f32>,
+
+    /// Where optimised images are cached between builds [default: .cache/images]
+    #[arg(long, value_name = "DIR")]
+    image_cache: Option<PathBuf>,
 
-    // Step 5: Render template
-    println!("\n{}", "Rendering template...".yellow());
-    let output = render_template(template_data)?;
-    
-    // Step 6: Write output
-    let output_path = "index.html";
-    fs::write(output_path, output)
-        .context(format!("Failed to write {}", output_path))?;
-    
-    let output_size = fs::metadata(output_path)?.len() / 1024;
-    println!("  {} Written {} ({}kb)", "→".green(), output_path, output_size);
-
-    // Success!
-    println!("\n{}", "=".repeat(50).dimmed());
-    println!("✨ {} Build complete!", "Success!".green().bold());
-    println!("🎯 Open index.html in any browser to play");
-    
-    Ok(())
+    /// Directory, relative to the page, that optimised images are written to [default: img]
+    #[arg(long, value_name = "DIR")]
+    image_out_dir: Option<PathBuf>,
+
+    /// Minify the embedded CSS and JS, compact the embedded JSON and report
+    /// the size of each asset before and after
+    #[arg(long, overrides_with = "no_release")]
+    release: bool,
+
+    /// Build an unminified page, overriding game.toml
+    #[arg(long, overrides_with = "release")]
+    no_release: bool,
+
+    /// Add the Content-Security-Policy meta tag, overriding game.toml
+    #[arg(long, overrides_with = "no_csp")]
+    csp: bool,
+
+    /// Leave out the Content-Security-Policy meta tag that allows only the
+    /// page's own inline scripts and styles
+    #[arg(long, overrides_with = "csp")]
+    no_csp: bool,
+
+    /// Also write a web app manifest, icons made from [pwa] icon and a
+    /// service worker that precaches the page and its images, so the game
+    /// can be installed and played offline
+    #[arg(long, overrides_with = "no_pwa")]
+    pwa: bool,
+
+    /// Don't write the offline app files, overriding game.toml
+    #[arg(long, overrides_with = "pwa")]
+    no_pwa: bool,
+
+    /// Write a self-contained page per question set, with only that set and
+    /// its images inlined, named <set key>.html next to --out, and make --out
+    /// an index page linking them
+    #[arg(long, overrides_with = "no_split")]
+    split: bool,
+
+    /// Bundle every set into one page, overriding game.toml
+    #[arg(long, overrides_with = "split")]
+    no_split: bool,
+
+    /// Make the page byte-for-byte reproducible: stamp it with
+    /// SOURCE_DATE_EPOCH, or the time of the HEAD commit, instead of the
+    /// current time. SOURCE_DATE_EPOCH is honoured even without this flag
+    #[arg(long, overrides_with = "no_reproducible")]
+    reproducible: bool,
+
+    /// Stamp the page with the current time, overriding game.toml
+    #[arg(long, overrides_with = "reproducible")]
+    no_reproducible: bool,
+
+    #[command(flatten)]
+    config: ConfigArgs,
 }
 
-fn load_question_sets() -> Result<HashMap<String, Value>> {
-    let mut question_sets = HashMap::new();
-    let data_dir = Path::new("data");
-    
-    for entry in WalkDir::new(data_dir)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        let filename = path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("");
-        
-        if filename.starts_with("questions") && filename.ends_with(".json") {
-            let key = path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-                .to_string();
-            
-            let content = fs::read_to_string(path)?;
-            let data: Value = serde_json::from_str(&content)?;
-            
-            // Get title for display
-            let title = data.get("metadata")
-                .and_then(|m| m.get("title"))
-                .and_then(|t| t.as_str())
-                .unwrap_or(&key);
-            
-            println!("    {} {}: {}", "•".dimmed(), key.cyan(), title.dimmed());
-            question_sets.insert(key, data);
+impl Args {
+    /// The manifest with this run's flags applied on top.
+    fn config(&self) -> Result<Config> {
+        let mut config = self.config.load()?;
+        let (paths, bundle) = (&mut config.paths, &mut config.bundle);
+        override_with(&mut paths.data_dir, &self.data_dir);
+        override_with(&mut paths.template, &self.template);
+        if self.theme.is_some() {
+            paths.theme = self.theme.clone();
         }
+        override_with(&mut bundle.lang, &self.lang);
+        override_with(&mut paths.output, &self.out);
+        override_with(&mut paths.asset_root, &self.asset_root);
+        if !self.css.is_empty() {
+            paths.css = self.css.clone();
+        }
+        if !self.js.is_empty() {
+            paths.js = self.js.clone();
+        }
+        if !self.include.is_empty() {
+            bundle.include = self.include.clone();
+        }
+        if !self.exclude.is_empty() {
+            bundle.exclude = self.exclude.clone();
+        }
+        if self.default_set.is_some() {
+            bundle.default_set = self.default_set.clone();
+        }
+        switch(&mut bundle.inline_images, self.inline_images, self.no_inline_images);
+        switch(&mut bundle.optimize_images, self.optimize_images, self.no_optimize_images);
+        switch(&mut bundle.webp, self.webp, self.no_webp);
+        switch(&mut bundle.reproducible, self.reproducible, self.no_reproducible);
+        switch(&mut bundle.release, self.release, self.no_release);
+        switch(&mut bundle.csp, self.csp, self.no_csp);
+        switch(&mut bundle.pwa, self.pwa, self.no_pwa);
+        switch(&mut bundle.split, self.split, self.no_split);
+        override_with(&mut bundle.max_image_dimension, &self.max_image_dimension);
+        override_with(&mut bundle.image_colors, &self.image_colors);
+        override_with(&mut bundle.webp_quality, &self.webp_quality);
+        override_with(&mut bundle.image_cache, &self.image_cache);
+        override_with(&mut bundle.image_out_dir, &self.image_out_dir);
+
+        Ok(config)
     }
-    
-    if question_sets.is_empty() {
-        anyhow::bail!("No question files found in data/");
-    }
-    
-    Ok(question_sets)
 }
 
-struct BuildInfo {
-    version: String,
-    timestamp: String,
-    timestamp_unix: i64,
+/// Applies a `--<flag>`/`--no-<flag>` pair; clap keeps only the last of
+/// the two given.
+fn switch(value: &mut bool, on: bool, off: bool) {
+    if on {
+        *value = true;
+    } else if off {
+        *value = false;
+    }
 }
 
-fn generate_build_info() -> BuildInfo {
-    let timestamp = Local::now();
-    
-    // Try to get git version
-    let version = Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
-        .output()
-        .ok()
-        .and_then(|output| String::from_utf8(output.stdout).ok())
-        .map(|s| format!("git-{}", s.trim()))
-        .unwrap_or_else(|| "dev-build".to_string());
-    
-    BuildInfo {
-        version,
-        timestamp: timestamp.to_rfc3339(),
-        timestamp_unix: timestamp.timestamp(),
+fn override_with<T: Clone>(value: &mut T, flag: &Option<T>) {
+    if let Some(flag) = flag {
+        *value = flag.clone();
     }
 }
 
-fn render_template(data: Value) -> Result<String> {
-    // Use the Handlebars template
-    let template_path = Path::new("templates/index.hbs");
-    
-    if !template_path.exists() {
-        anyhow::bail!("Template not found at templates/index.hbs");
+fn main() {
+    let args = Args::parse();
+    let result = args.config().and_then(|config| {
+        if args.config.print(&config)? {
+            return Ok(());
+        }
+        bundle::run(&config)
+    });
+    if let Err(e) = result {
+        eprintln!("{} {}", "Error:".red().bold(), e);
+        std::process::exit(1);
     }
-    
-    let template_content = fs::read_to_string(template_path)?;
-    
-    let mut handlebars = Handlebars::new();
-    // Disable HTML escaping since we're embedding raw HTML/JS/CSS
-    handlebars.register_escape_fn(handlebars::no_escape);
-    
-    let rendered = handlebars.render_template(&template_content, &data)
-        .context("Failed to render template")?;
-    
-    Ok(rendered)
 }
-
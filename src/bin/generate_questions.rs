@@ -0,0 +1,520 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueEnum};
+use colored::*;
+use jsonschema::JSONSchema;
+use just_learn_just_build_tools::factcheck::check_food_web;
+use just_learn_just_build_tools::foodweb::{FoodWeb, Node};
+use just_learn_just_build_tools::validation::{check_question_sets, check_schema, Severity};
+use just_learn_just_build_tools::{Difficulty, Metadata, Mode, Question, QuestionSet, QuestionSetFile};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Generates question sets from the food web graph", long_about = None)]
+struct Args {
+    /// Where to read the food web from (markdown with a mermaid block, .mmd or .json)
+    #[arg(long, default_value = "README.md")]
+    foodweb: PathBuf,
+
+    /// Output file, e.g. data/questions_generated.json. Printed to stdout if omitted
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+
+    /// Number of questions to generate
+    #[arg(short = 'n', long, default_value_t = 10)]
+    count: usize,
+
+    /// Choices per question (2-6, the schema's limits)
+    #[arg(long, default_value_t = 4, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..=6))]
+    choices: usize,
+
+    /// Question kinds to mix (repeatable). Defaults to all of them
+    #[arg(long = "kind", value_enum)]
+    kinds: Vec<Kind>,
+
+    /// Random seed; the same seed, food web and options give the same file
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Game mode of the generated set
+    #[arg(long, value_enum, default_value_t = SetMode::Easy)]
+    mode: SetMode,
+
+    /// Title shown on the start screen tile
+    #[arg(long, default_value = "Food Web - Generated Questions")]
+    title: String,
+
+    /// Intended audience
+    #[arg(long, default_value = "5-8 years")]
+    target_age: String,
+
+    /// Question ids are <PREFIX>_1, <PREFIX>_2, ...
+    #[arg(long, default_value = "gen")]
+    id_prefix: String,
+
+    /// Directory holding the XX.png images. Nodes without an image are never the subject of a question
+    #[arg(long, default_value = ".")]
+    image_dir: PathBuf,
+
+    /// Node id to leave out entirely, e.g. FH (fishing). Repeatable
+    #[arg(long = "exclude", value_name = "ID")]
+    exclude: Vec<String>,
+
+    /// Schema the generated set is checked against before it is written
+    #[arg(long, default_value = "data/schema.json")]
+    schema: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+enum Kind {
+    /// "Which of these eats krill?"
+    PredatorOf,
+    /// "Which of these is eaten by penguins?"
+    PreyOf,
+    /// "Where in the food chain do you find squid?"
+    TrophicLevel,
+    /// "Which of these is NOT eaten by seals?"
+    NotEatenBy,
+}
+
+impl Kind {
+    fn slug(self) -> &'static str {
+        match self {
+            Kind::PredatorOf => "predator-of",
+            Kind::PreyOf => "prey-of",
+            Kind::TrophicLevel => "trophic-level",
+            Kind::NotEatenBy => "not-eaten-by",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SetMode {
+    Easy,
+    Hard,
+}
+
+/// A question to be written: its kind, the node shown in `image1`, and the
+/// node that answers it (none for trophic-level questions).
+#[derive(Clone, Copy)]
+struct Plan<'a> {
+    kind: Kind,
+    subject: &'a Node,
+    answer: Option<&'a Node>,
+}
+
+/// The food web restricted to the nodes questions may mention.
+struct Generator<'a> {
+    web: &'a FoodWeb,
+    nodes: Vec<&'a Node>,
+    images: HashSet<&'a str>,
+    levels: HashMap<&'a str, usize>,
+    choices: usize,
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("{} {}", "Error:".red().bold(), e);
+        process::exit(1);
+    }
+}
+
+/// Generates the question set, checks it and writes it out.
+fn run(args: &Args) -> Result<()> {
+    let web = FoodWeb::load(&args.foodweb)?;
+    for id in &args.exclude {
+        if web.node(id).is_none() {
+            anyhow::bail!("--exclude {}: no such node in {}", id, args.foodweb.display());
+        }
+    }
+    let generator = Generator::new(&web, args);
+    if generator.images.is_empty() {
+        anyhow::bail!("No node images (e.g. KR.png) found in {}", args.image_dir.display());
+    }
+
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let kinds = if args.kinds.is_empty() {
+        Kind::value_variants().to_vec()
+    } else {
+        args.kinds.clone()
+    };
+
+    let plans = generator.select(&kinds, args.count, &mut rng);
+    if plans.len() < args.count {
+        eprintln!(
+            "{} Only {} distinct questions can be made from this food web with these options",
+            "⚠️".yellow(),
+            plans.len()
+        );
+    }
+
+    let difficulty = match args.mode {
+        SetMode::Easy => Difficulty::Easy,
+        SetMode::Hard => Difficulty::Hard,
+    };
+    let questions = plans
+        .iter()
+        .enumerate()
+        .map(|(idx, plan)| {
+            let mut question = generator.question(plan, &mut rng);
+            question.id = format!("{}_{}", args.id_prefix, idx + 1);
+            question.difficulty = Some(difficulty);
+            question
+        })
+        .collect();
+
+    let set = QuestionSet {
+        metadata: Metadata {
+            title: args.title.clone(),
+            description: Some(format!(
+                "Generated from the food web in {} with seed {}.",
+                args.foodweb.display(),
+                seed
+            )),
+            version: "1.0.0".to_string(),
+            target_age: args.target_age.clone(),
+            subject: "Marine Biology".to_string(),
+            mode: match args.mode {
+                SetMode::Easy => Mode::Easy,
+                SetMode::Hard => Mode::Hard,
+            },
+        },
+        questions,
+    };
+
+    check_generated(&set, args, &web)?;
+
+    let json = serde_json::to_string_pretty(&set)? + "\n";
+    match &args.out {
+        Some(path) => {
+            fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "{} Wrote {} questions to {} (seed {})",
+                "✅".green(),
+                set.questions.len(),
+                path.display(),
+                seed
+            );
+        }
+        None => {
+            print!("{}", json);
+            eprintln!("{} Generated {} questions (seed {})", "✅".green(), set.questions.len(), seed);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the generated set through the schema, the semantic rules and the
+/// food-web fact check, so a bug here can never produce a file `validate`
+/// rejects.
+fn check_generated(set: &QuestionSet, args: &Args, web: &FoodWeb) -> Result<()> {
+    let schema_content = fs::read_to_string(&args.schema)
+        .with_context(|| format!("Failed to read schema at {}", args.schema.display()))?;
+    let schema: Value = serde_json::from_str(&schema_content).context("Failed to parse schema.json")?;
+    let compiled = JSONSchema::compile(&schema)
+        .map_err(|e| anyhow!("Failed to compile JSON schema: {}", e))?;
+
+    let path = args.out.clone().unwrap_or_else(|| PathBuf::from("<stdout>"));
+    let file = QuestionSetFile {
+        key: path.file_stem().and_then(|s| s.to_str()).unwrap_or("generated").to_string(),
+        path: path.clone(),
+        set: set.clone(),
+    };
+    let files = [file];
+    let diagnostics: Vec<_> = check_schema(&path, &serde_json::to_value(set)?, &compiled)
+        .into_iter()
+        .chain(check_question_sets(&files))
+        .chain(check_food_web(&files, web))
+        .collect();
+
+    for d in &diagnostics {
+        let symbol = match d.severity {
+            Severity::Error => "❌",
+            Severity::Warning => "⚠️",
+        };
+        eprintln!("{} {}[{}] {}: {}", symbol, d.severity, d.rule, d.pointer, d.message);
+    }
+    if diagnostics.iter().any(|d| d.is_error()) {
+        anyhow::bail!("The generated question set failed validation");
+    }
+    Ok(())
+}
+
+impl<'a> Generator<'a> {
+    fn new(web: &'a FoodWeb, args: &Args) -> Self {
+        let nodes: Vec<&Node> = web
+            .nodes()
+            .iter()
+            .filter(|n| !args.exclude.contains(&n.id))
+            .collect();
+        let images = nodes
+            .iter()
+            .filter(|n| args.image_dir.join(image_name(n)).is_file())
+            .map(|n| n.id.as_str())
+            .collect();
+        Self {
+            web,
+            nodes,
+            images,
+            levels: web.trophic_levels(),
+            choices: args.choices,
+        }
+    }
+
+    /// Whether any edge, feeding or detritus, joins `a` and `b`.
+    fn adjacent(&self, a: &str, b: &str) -> bool {
+        self.web
+            .edges()
+            .iter()
+            .any(|e| (e.from == a && e.to == b) || (e.from == b && e.to == a))
+    }
+
+    /// Nodes with no link at all to `subject`: safe distractors for any
+    /// question about it.
+    fn unrelated(&self, subject: &Node) -> Vec<&'a Node> {
+        self.nodes
+            .iter()
+            .copied()
+            .filter(|n| n.id != subject.id && !self.adjacent(&n.id, &subject.id))
+            .collect()
+    }
+
+    fn prey(&self, subject: &'a Node) -> Vec<&'a Node> {
+        self.web.prey_of(&subject.id).filter(|n| self.nodes.contains(n)).collect()
+    }
+
+    fn predators(&self, subject: &'a Node) -> Vec<&'a Node> {
+        self.web.predators_of(&subject.id).filter(|n| self.nodes.contains(n)).collect()
+    }
+
+    fn max_level(&self) -> usize {
+        self.levels.values().copied().max().unwrap_or(0)
+    }
+
+    /// Every question of `kind` there are enough choices for.
+    fn candidates(&self, kind: Kind) -> Vec<Plan<'a>> {
+        let distractors = self.choices - 1;
+        let mut plans = Vec::new();
+        for &subject in self.nodes.iter().filter(|n| self.images.contains(n.id.as_str())) {
+            let plan = |answer| Plan { kind, subject, answer };
+            match kind {
+                Kind::PredatorOf | Kind::PreyOf => {
+                    if self.unrelated(subject).len() < distractors {
+                        continue;
+                    }
+                    let answers = match kind {
+                        Kind::PredatorOf => self.predators(subject),
+                        _ => self.prey(subject),
+                    };
+                    plans.extend(answers.into_iter().map(|a| plan(Some(a))));
+                }
+                Kind::NotEatenBy => {
+                    if self.prey(subject).len() < distractors {
+                        continue;
+                    }
+                    plans.extend(self.unrelated(subject).into_iter().map(|a| plan(Some(a))));
+                }
+                Kind::TrophicLevel => {
+                    if self.levels.contains_key(subject.id.as_str()) && self.max_level() >= 2 {
+                        plans.push(plan(None));
+                    }
+                }
+            }
+        }
+        plans
+    }
+
+    /// Picks up to `count` plans, taking kinds in turn so the mix stays
+    /// even, and never asking the same kind of question about a node twice.
+    fn select(&self, kinds: &[Kind], count: usize, rng: &mut ChaCha8Rng) -> Vec<Plan<'a>> {
+        let mut pools: Vec<Vec<Plan>> = kinds
+            .iter()
+            .map(|&kind| {
+                let mut pool = self.candidates(kind);
+                pool.shuffle(rng);
+                pool
+            })
+            .collect();
+
+        let mut used = HashSet::new();
+        let mut plans = Vec::new();
+        while plans.len() < count && pools.iter().any(|p| !p.is_empty()) {
+            for pool in pools.iter_mut() {
+                if plans.len() == count {
+                    break;
+                }
+                while let Some(plan) = pool.pop() {
+                    if used.insert((plan.kind, plan.subject.id.as_str())) {
+                        plans.push(plan);
+                        break;
+                    }
+                }
+            }
+        }
+        plans
+    }
+
+    fn question(&self, plan: &Plan<'a>, rng: &mut ChaCha8Rng) -> Question {
+        let Some(correct) = plan.answer else {
+            return self.trophic_question(plan, rng);
+        };
+        let subject = plan.subject;
+        let name = lower(subject);
+
+        let (text, mut choices, explanation) = match plan.kind {
+            Kind::PredatorOf => (
+                format!("Which of these eats {}?", name),
+                self.unrelated(subject),
+                format!(
+                    "In the food web energy flows from {} to {}. Everything that eats {}: {}.",
+                    name,
+                    lower(correct),
+                    name,
+                    join_names(&self.predators(subject))
+                ),
+            ),
+            Kind::PreyOf => (
+                format!("Which of these is eaten by {}?", name),
+                self.unrelated(subject),
+                format!(
+                    "In the food web energy flows from {} to {}. Food eaten by {}: {}.",
+                    lower(correct),
+                    name,
+                    name,
+                    join_names(&self.prey(subject))
+                ),
+            ),
+            Kind::NotEatenBy => (
+                format!("Which of these is NOT eaten by {}?", name),
+                self.prey(subject),
+                format!(
+                    "{} and {} are not linked in the food web. Food eaten by {}: {}.",
+                    correct.name(),
+                    name,
+                    name,
+                    join_names(&self.prey(subject))
+                ),
+            ),
+            Kind::TrophicLevel => unreachable!("trophic-level questions have no answer node"),
+        };
+
+        choices.shuffle(rng);
+        choices.truncate(self.choices - 1);
+        let correct_answer = rng.gen_range(0..=choices.len());
+        choices.insert(correct_answer, correct);
+
+        Question {
+            id: String::new(),
+            question: text,
+            image1: Some(image_name(subject)),
+            image2: self
+                .images
+                .contains(correct.id.as_str())
+                .then(|| image_name(correct)),
+            choices: choices.iter().map(|n| n.name().to_string()).collect(),
+            correct_answer,
+            explanation,
+            category: "food-chain".to_string(),
+            difficulty: None,
+            tags: vec!["generated".to_string(), plan.kind.slug().to_string()],
+        }
+    }
+
+    /// Asks for the subject's trophic level; the choices are level names in
+    /// food-chain order.
+    fn trophic_question(&self, plan: &Plan<'a>, rng: &mut ChaCha8Rng) -> Question {
+        let subject = plan.subject;
+        let name = lower(subject);
+        let level = self.levels[subject.id.as_str()];
+
+        let mut levels: Vec<usize> = (1..=self.max_level()).filter(|&l| l != level).collect();
+        levels.shuffle(rng);
+        levels.truncate(self.choices - 1);
+        levels.push(level);
+        levels.sort_unstable();
+        let correct_answer = levels.iter().position(|&l| l == level).unwrap_or(0);
+
+        let explanation = if level == 1 {
+            format!(
+                "{}: nothing in the food web is eaten by {}, so food chains start here.",
+                level_name(level),
+                name
+            )
+        } else {
+            format!(
+                "{}: the shortest food chain leading to {} is {}.",
+                level_name(level),
+                name,
+                self.chain(subject).join(" → ")
+            )
+        };
+
+        Question {
+            id: String::new(),
+            question: format!("Where in the food chain do you find {}?", name),
+            image1: Some(image_name(subject)),
+            image2: None,
+            choices: levels.into_iter().map(level_name).collect(),
+            correct_answer,
+            explanation,
+            category: "trophic-levels".to_string(),
+            difficulty: None,
+            tags: vec!["generated".to_string(), plan.kind.slug().to_string()],
+        }
+    }
+
+    /// The shortest food chain from a producer up to `node`, as lowercase names.
+    fn chain(&self, node: &'a Node) -> Vec<String> {
+        let mut chain = vec![lower(node)];
+        let mut current = node;
+        while let Some(&level) = self.levels.get(current.id.as_str()) {
+            let Some(prey) = self
+                .web
+                .prey_of(&current.id)
+                .find(|p| self.levels.get(p.id.as_str()) == Some(&(level - 1)))
+            else {
+                break;
+            };
+            chain.push(lower(prey));
+            current = prey;
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+fn image_name(node: &Node) -> String {
+    format!("{}.png", node.id)
+}
+
+fn lower(node: &Node) -> String {
+    node.name().to_lowercase()
+}
+
+fn level_name(level: usize) -> String {
+    match level {
+        1 => "Producer".to_string(),
+        2 => "Primary consumer".to_string(),
+        3 => "Secondary consumer".to_string(),
+        4 => "Tertiary consumer".to_string(),
+        n => format!("Level {} consumer", n),
+    }
+}
+
+/// "a, b and c"
+fn join_names(nodes: &[&Node]) -> String {
+    let names: Vec<String> = nodes.iter().map(|n| lower(n)).collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        Some((last, _)) => last.clone(),
+        None => String::new(),
+    }
+}
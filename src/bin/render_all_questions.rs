@@ -1,102 +1,90 @@
-use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::fs;
-use std::path::Path;
+use anyhow::Result;
+use clap::Parser;
+use just_learn_just_build_tools::config::ConfigArgs;
+use just_learn_just_build_tools::load_question_sets;
+use std::path::PathBuf;
 
-#[derive(Debug, Deserialize)]
-struct QuestionSet {
-    metadata: Metadata,
-    questions: Vec<Question>,
-}
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Prints every question of every set and checks their images exist", long_about = None)]
+struct Args {
+    /// Directory containing the questions*.json files [default: data]
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
 
-#[derive(Debug, Deserialize)]
-struct Metadata {
-    title: String,
-    description: String,
-    mode: String,
-    #[serde(rename = "targetAge")]
-    target_age: String,
-    subject: String,
-}
+    /// Directory the question images are read from [default: .]
+    #[arg(long, value_name = "DIR")]
+    asset_root: Option<PathBuf>,
 
-#[derive(Debug, Deserialize)]
-struct Question {
-    id: String,
-    question: String,
-    image1: Option<String>,
-    image2: Option<String>,
-    choices: Vec<String>,
-    #[serde(rename = "correctAnswer")]
-    correct_answer: usize,
-    explanation: String,
-    category: String,
-    difficulty: String,
+    #[command(flatten)]
+    config: ConfigArgs,
 }
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut config = args.config.load()?;
+    if let Some(dir) = &args.data_dir {
+        config.paths.data_dir = dir.clone();
+    }
+    if let Some(dir) = &args.asset_root {
+        config.paths.asset_root = dir.clone();
+    }
+    if args.config.print(&config)? {
+        return Ok(());
+    }
+
     println!("🔍 Rendering All Ross Sea Questions");
     println!("====================================\n");
 
-    let data_dir = Path::new("data");
-    let entries = fs::read_dir(data_dir)?;
+    let question_sets = load_question_sets(&config.paths.data_dir)?;
+    let asset_root = config.paths.asset_root.as_path();
 
     let mut total_questions = 0;
     let mut question_sets_found = 0;
 
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+    for file in &question_sets {
+        let filename = file.filename();
+        let question_set = &file.set;
+
+        println!("📁 Processing: {}", filename);
+        
+        println!("   📚 Title: {}", question_set.metadata.title);
+        println!("   🎯 Mode: {}", question_set.metadata.mode);
+        println!("   👥 Target Age: {}", question_set.metadata.target_age);
+        println!("   📊 Questions: {}\n", question_set.questions.len());
         
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-            let filename = path.file_name().unwrap().to_string_lossy();
+        // Render each question
+        for (idx, question) in question_set.questions.iter().enumerate() {
+            println!("   Question {} (ID: {}):", idx + 1, question.id);
+            println!("   ├─ Text: {}", question.question);
+            println!("   ├─ Category: {}", question.category);
+            if let Some(difficulty) = question.difficulty {
+                println!("   ├─ Difficulty: {}", difficulty);
+            }
             
-            if filename.starts_with("questions_ross_sea") {
-                println!("📁 Processing: {}", filename);
-                
-                let content = fs::read_to_string(&path)
-                    .with_context(|| format!("Failed to read {}", path.display()))?;
-                
-                let question_set: QuestionSet = serde_json::from_str(&content)
-                    .with_context(|| format!("Failed to parse {}", path.display()))?;
-                
-                println!("   📚 Title: {}", question_set.metadata.title);
-                println!("   🎯 Mode: {}", question_set.metadata.mode);
-                println!("   👥 Target Age: {}", question_set.metadata.target_age);
-                println!("   📊 Questions: {}\n", question_set.questions.len());
-                
-                // Render each question
-                for (idx, question) in question_set.questions.iter().enumerate() {
-                    println!("   Question {} (ID: {}):", idx + 1, question.id);
-                    println!("   ├─ Text: {}", question.question);
-                    println!("   ├─ Category: {}", question.category);
-                    println!("   ├─ Difficulty: {}", question.difficulty);
-                    
-                    if let Some(img1) = &question.image1 {
-                        println!("   ├─ Question Image: {}", img1);
-                    }
-                    
-                    if let Some(img2) = &question.image2 {
-                        println!("   ├─ Answer Image: {}", img2);
-                    }
-                    
-                    println!("   ├─ Choices:");
-                    for (choice_idx, choice) in question.choices.iter().enumerate() {
-                        let marker = if choice_idx == question.correct_answer { "✅" } else { "  " };
-                        println!("   │  {} [{}] {}", marker, choice_idx, choice);
-                    }
-                    
-                    println!("   └─ Explanation: {}\n", question.explanation);
-                }
-                
-                total_questions += question_set.questions.len();
-                question_sets_found += 1;
-                
-                println!("   ✅ Successfully rendered {} questions from {}\n", 
-                         question_set.questions.len(), filename);
-                println!("   {}", "─".repeat(60));
-                println!();
+            if let Some(img1) = &question.image1 {
+                println!("   ├─ Question Image: {}", img1);
             }
+            
+            if let Some(img2) = &question.image2 {
+                println!("   ├─ Answer Image: {}", img2);
+            }
+            
+            println!("   ├─ Choices:");
+            for (choice_idx, choice) in question.choices.iter().enumerate() {
+                let marker = if choice_idx == question.correct_answer { "✅" } else { "  " };
+                println!("   │  {} [{}] {}", marker, choice_idx, choice);
+            }
+            
+            println!("   └─ Explanation: {}\n", question.explanation);
         }
+        
+        total_questions += question_set.questions.len();
+        question_sets_found += 1;
+        
+        println!("   ✅ Successfully rendered {} questions from {}\n", 
+                 question_set.questions.len(), filename);
+        println!("   {}", "─".repeat(60));
+        println!();
     }
     
     println!("====================================");
@@ -109,30 +97,17 @@ fn main() -> Result<()> {
     println!("\n🖼️  Validating Image References:");
     let mut missing_images = Vec::new();
     
-    // Re-read to check images
-    for entry in fs::read_dir(data_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-            let filename = path.file_name().unwrap().to_string_lossy();
+    for file in &question_sets {
+        for question in &file.set.questions {
+            if let Some(img1) = &question.image1 {
+                if !asset_root.join(img1).exists() {
+                    missing_images.push((question.id.clone(), img1.clone(), "image1"));
+                }
+            }
             
-            if filename.starts_with("questions_ross_sea") {
-                let content = fs::read_to_string(&path)?;
-                let question_set: QuestionSet = serde_json::from_str(&content)?;
-                
-                for question in &question_set.questions {
-                    if let Some(img1) = &question.image1 {
-                        if !Path::new(img1).exists() {
-                            missing_images.push((question.id.clone(), img1.clone(), "image1"));
-                        }
-                    }
-                    
-                    if let Some(img2) = &question.image2 {
-                        if !Path::new(img2).exists() {
-                            missing_images.push((question.id.clone(), img2.clone(), "image2"));
-                        }
-                    }
+            if let Some(img2) = &question.image2 {
+                if !asset_root.join(img2).exists() {
+                    missing_images.push((question.id.clone(), img2.clone(), "image2"));
                 }
             }
         }
@@ -1,110 +1,133 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chromiumoxide::browser::{Browser, BrowserConfig};
-use chromiumoxide::handler::viewport::Viewport;
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetDeviceMetricsOverrideParams, SetTouchEmulationEnabledParams,
+};
 use chromiumoxide::page::ScreenshotParams;
+use clap::Parser;
+use futures::StreamExt;
+use just_learn_just_build_tools::config::ConfigArgs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Screenshots the bundled page at each configured viewport and checks the footer", long_about = None)]
+struct Args {
+    /// Page to open [default: index.html]
+    #[arg(long, value_name = "PATH")]
+    page: Option<PathBuf>,
+
+    /// Only capture this viewport, by name from game.toml (repeatable)
+    #[arg(long, value_name = "NAME")]
+    viewport: Vec<String>,
+
+    /// Where to write the screenshots [default: .]
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut config = args.config.load()?;
+    if let Some(page) = &args.page {
+        config.paths.output = page.clone();
+    }
+    if let Some(dir) = &args.out_dir {
+        config.paths.screenshots = dir.clone();
+    }
+    let viewports = if args.viewport.is_empty() {
+        config.viewports()
+    } else {
+        args.viewport.iter().map(|name| config.viewport(name)).collect::<Result<Vec<_>>>()?
+    };
+    if args.config.print(&config)? {
+        return Ok(());
+    }
+
     println!("🎨 Layout Screenshot Test");
     println!("========================");
 
     // Launch browser
-    let (browser, mut handler) = Browser::launch(BrowserConfig::builder().build()?).await?;
+    let (mut browser, mut handler) = Browser::launch(BrowserConfig::builder().build().map_err(|e| anyhow!(e))?).await?;
     
     tokio::spawn(async move {
         while handler.next().await.is_some() {}
     });
 
     // Load the page
-    let page_path = std::env::current_dir()?.join("index.html");
+    let page_path = std::fs::canonicalize(&config.paths.output)?;
     let url = format!("file://{}", page_path.display());
+    std::fs::create_dir_all(&config.paths.screenshots)?;
     
-    // Test desktop viewport
-    println!("\n📱 Testing Desktop Layout (1280x800)...");
-    let desktop_page = browser.new_page(url.clone()).await?;
-    desktop_page.set_viewport(Viewport {
-        width: 1280,
-        height: 800,
-        device_scale_factor: Some(1.0),
-        emulating_mobile: false,
-        ..Default::default()
-    }).await?;
-    
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-    
-    // Take desktop screenshot
-    let desktop_screenshot = desktop_page.screenshot(ScreenshotParams::default()).await?;
-    std::fs::write("screenshot-desktop-layout.png", desktop_screenshot)?;
-    println!("✅ Desktop screenshot saved to screenshot-desktop-layout.png");
-    
-    // Check footer on desktop
-    let desktop_footer = desktop_page.evaluate(r#"
-        (() => {
-            const footer = document.querySelector('.copyright-footer');
-            if (footer) {
-                const rect = footer.getBoundingClientRect();
-                return {
-                    visible: true,
-                    height: rect.height,
-                    bottom: window.innerHeight - rect.bottom,
-                    text: footer.textContent.trim()
-                };
-            }
-            return { visible: false };
-        })()
-    "#).await?.into_value::<serde_json::Value>()?;
-    
-    println!("Desktop footer: {:?}", desktop_footer);
-    
-    // Test mobile viewport (iPhone 14)
-    println!("\n📱 Testing Mobile Layout (390x844)...");
-    let mobile_page = browser.new_page(url).await?;
-    mobile_page.set_viewport(Viewport {
-        width: 390,
-        height: 844,
-        device_scale_factor: Some(3.0),
-        emulating_mobile: true,
-        is_mobile: Some(true),
-        has_touch: Some(true),
-        ..Default::default()
-    }).await?;
-    
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-    
-    // Take mobile screenshot
-    let mobile_screenshot = mobile_page.screenshot(ScreenshotParams::default()).await?;
-    std::fs::write("screenshot-mobile-layout.png", mobile_screenshot)?;
-    println!("✅ Mobile screenshot saved to screenshot-mobile-layout.png");
-    
-    // Check footer on mobile
-    let mobile_footer = mobile_page.evaluate(r#"
-        (() => {
-            const footer = document.querySelector('.copyright-footer');
-            if (footer) {
-                const rect = footer.getBoundingClientRect();
-                const styles = window.getComputedStyle(footer);
-                return {
-                    visible: true,
-                    height: rect.height,
-                    fontSize: styles.fontSize,
-                    padding: styles.padding,
-                    bottom: window.innerHeight - rect.bottom,
-                    overlapping: rect.bottom > window.innerHeight,
-                    text: footer.textContent.trim().substring(0, 100)
-                };
+    for viewport in &viewports {
+        println!(
+            "\n📱 Testing {} Layout ({}x{})...",
+            viewport.name, viewport.width, viewport.height
+        );
+        let page = browser.new_page(url.clone()).await?;
+        // chromiumoxide 0.5 has no Page::set_viewport, which this tool used
+        // to call and so never built; emulate the screen over the DevTools
+        // protocol instead
+        page
+            .execute(SetDeviceMetricsOverrideParams::new(
+                viewport.width as i64,
+                viewport.height as i64,
+                viewport.scale,
+                viewport.mobile,
+            ))
+            .await?;
+        if viewport.mobile {
+            page
+                .execute(SetTouchEmulationEnabledParams::new(true))
+                .await?;
+        }
+        
+        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        
+        // Take screenshot
+        let screenshot = page.screenshot(ScreenshotParams::default()).await?;
+        let screenshot_path = config
+            .paths
+            .screenshots
+            .join(format!("screenshot-{}-layout.png", viewport.name));
+        std::fs::write(&screenshot_path, screenshot)?;
+        println!("✅ {} screenshot saved to {}", viewport.name, screenshot_path.display());
+        
+        // Check footer
+        let footer = page.evaluate(r#"
+            (() => {
+                const footer = document.querySelector('.copyright-footer');
+                if (footer) {
+                    const rect = footer.getBoundingClientRect();
+                    const styles = window.getComputedStyle(footer);
+                    return {
+                        visible: true,
+                        height: rect.height,
+                        fontSize: styles.fontSize,
+                        padding: styles.padding,
+                        bottom: window.innerHeight - rect.bottom,
+                        overlapping: rect.bottom > window.innerHeight,
+                        text: footer.textContent.trim().substring(0, 100)
+                    };
+                }
+                return { visible: false };
+            })()
+        "#).await?.into_value::<serde_json::Value>()?;
+        
+        println!("{} footer: {:?}", viewport.name, footer);
+        
+        // Check if footer is reasonable size on small screens
+        if viewport.mobile {
+            if let Some(height) = footer.get("height").and_then(|h| h.as_f64()) {
+                if height > 50.0 {
+                    println!("⚠️  Footer height ({:.1}px) might be too tall for mobile", height);
+                } else {
+                    println!("✅ Footer height ({:.1}px) is reasonable", height);
+                }
             }
-            return { visible: false };
-        })()
-    "#).await?.into_value::<serde_json::Value>()?;
-    
-    println!("Mobile footer: {:?}", mobile_footer);
-    
-    // Check if footer is reasonable size
-    if let Some(height) = mobile_footer.get("height").and_then(|h| h.as_f64()) {
-        if height > 50.0 {
-            println!("⚠️  Footer height ({:.1}px) might be too tall for mobile", height);
-        } else {
-            println!("✅ Footer height ({:.1}px) is reasonable", height);
         }
     }
     
@@ -113,4 +136,4 @@ async fn main() -> Result<()> {
     println!("\n✅ Layout test complete! Check the screenshot files.");
     
     Ok(())
-}
\ No newline at end of file
+}
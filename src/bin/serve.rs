@@ -0,0 +1,427 @@
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use colored::*;
+use just_learn_just_build_tools::bundle;
+use just_learn_just_build_tools::config::{Config, ConfigArgs, DEFAULT_CONFIG};
+use just_learn_just_build_tools::images::mime_type;
+use just_learn_just_build_tools::validation::Diagnostic;
+use just_learn_just_build_tools::validator::Validator;
+use just_learn_just_build_tools::watch::Watcher;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+/// Where pages listen for rebuilds, as server-sent events.
+const EVENTS_PATH: &str = "/__livereload";
+
+/// The client side of live reload, added to every page built.
+const CLIENT_SCRIPT: &str = include_str!("../../js/live-reload.js");
+
+/// How often an idle event stream gets a comment, so closed tabs are noticed.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Largest request head read before giving up on a connection.
+const MAX_REQUEST: usize = 16 * 1024;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Serves the game locally, rebuilding and reloading it when its sources change", long_about = None)]
+struct Args {
+    /// Address to listen on [default: 127.0.0.1]
+    #[arg(long, value_name = "HOST")]
+    host: Option<String>,
+
+    /// Port to listen on [default: 8000]
+    #[arg(short, long, value_name = "PORT")]
+    port: Option<u16>,
+
+    /// Directory the served pages are built in [default: .cache/serve]
+    #[arg(long, value_name = "DIR")]
+    dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+impl Args {
+    /// The manifest with this run's flags applied on top, set up to build
+    /// into the serve directory.
+    fn config(&self) -> Result<Config> {
+        let mut config = self.config.load()?;
+        let serve = &mut config.serve;
+        if let Some(host) = &self.host {
+            serve.host = host.clone();
+        }
+        if let Some(port) = self.port {
+            serve.port = port;
+        }
+        if let Some(dir) = &self.dir {
+            serve.dir = dir.clone();
+        }
+        // Leave paths.output alone, and don't install a service worker: its
+        // cache would keep serving the previous build
+        let page = config.paths.output.file_name().unwrap_or("index.html".as_ref());
+        config.paths.output = config.serve.dir.join(page);
+        config.bundle.pwa = false;
+        Ok(config)
+    }
+
+    /// The manifest, watched like the sources. It may not exist yet.
+    fn config_file(&self) -> PathBuf {
+        self.config.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG))
+    }
+}
+
+/// What pages are told after each build.
+#[derive(Debug, Clone, Serialize)]
+struct Status {
+    /// Number of the last successful build. Numbering starts at the time
+    /// the server started, so pages built by an earlier run never match.
+    build: u64,
+    /// Why the last build failed, if it did.
+    errors: Option<Errors>,
+}
+
+/// What the overlay shows.
+#[derive(Debug, Clone, Serialize)]
+struct Errors {
+    title: String,
+    messages: Vec<String>,
+}
+
+/// State shared by the connections and the builder.
+struct Server {
+    status: Mutex<Status>,
+    updates: broadcast::Sender<Status>,
+    /// The configuration of the last build, for where to find files.
+    config: Mutex<Config>,
+}
+
+impl Server {
+    /// Records the outcome of a build and tells every open page.
+    fn publish(&self, status: Status) {
+        *self.status.lock().unwrap() = status.clone();
+        // No receivers just means no page is open
+        let _ = self.updates.send(status);
+    }
+
+    fn fail(&self, title: &str, messages: Vec<String>) {
+        println!("\n{} {}", "❌".red(), title.red().bold());
+        for message in &messages {
+            println!("  {} {}", "•".dimmed(), message);
+        }
+        let build = self.status.lock().unwrap().build;
+        self.publish(Status {
+            build,
+            errors: Some(Errors {
+                title: title.to_string(),
+                messages,
+            }),
+        });
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(args).await {
+        eprintln!("{} {}", "Error:".red().bold(), e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    let config = args.config()?;
+    if args.config.print(&config)? {
+        return Ok(());
+    }
+
+    let address = format!("{}:{}", config.serve.host, config.serve.port);
+    let listener = TcpListener::bind(&address)
+        .await
+        .map_err(|e| anyhow!("Failed to listen on {}: {}", address, e))?;
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (updates, _) = broadcast::channel(16);
+    let server = Arc::new(Server {
+        status: Mutex::new(Status {
+            build: started,
+            errors: None,
+        }),
+        updates,
+        config: Mutex::new(config),
+    });
+
+    println!("{}", "🌐 Just Learn Just Game - Development Server".blue().bold());
+    println!("{}", "=".repeat(50).dimmed());
+
+    // Build once before answering, so the first request gets a current page
+    let (built, first_build) = mpsc::channel();
+    let builder = server.clone();
+    thread::spawn(move || watch(args, builder, built));
+    let _ = tokio::task::spawn_blocking(move || first_build.recv()).await;
+
+    println!("\n{}", "=".repeat(50).dimmed());
+    println!("🎯 Serving on {}", format!("http://{}/", address).cyan().bold());
+    println!("👀 Watching for changes (Ctrl+C to stop)");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            // A failed connection only affects that request
+            let _ = handle(stream, server).await;
+        });
+    }
+}
+
+/// Builds, then rebuilds whenever a source changes. Signals `built` after
+/// the first build.
+fn watch(args: Args, server: Arc<Server>, built: mpsc::Sender<()>) {
+    let mut watcher = Watcher::new(vec![args.config_file()]);
+    loop {
+        if let Some(config) = rebuild(&args, &server) {
+            watcher.set_paths(watched_paths(&args, &config));
+        }
+        let _ = built.send(());
+
+        let changed = watcher.wait();
+        let names: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+        println!("\n{} Changed: {}", "🔄".yellow(), names.join(", ").cyan());
+    }
+}
+
+/// Everything a build reads. The question images are served straight from
+/// `paths.asset-root`, so they are not watched.
+fn watched_paths(args: &Args, config: &Config) -> Vec<PathBuf> {
+    let paths = &config.paths;
+    let mut watched = vec![
+        args.config_file(),
+        paths.data_dir.clone(),
+        paths.schema(),
+        paths.template.clone(),
+        paths.index_template.clone(),
+        paths.partials.clone(),
+        paths.strings.clone(),
+    ];
+    watched.extend(paths.theme.clone());
+    watched.extend(paths.css.iter().cloned());
+    watched.extend(paths.js.iter().cloned());
+    if config.lint.check_foodweb {
+        watched.push(paths.foodweb.clone());
+    }
+    watched.sort();
+    watched.dedup();
+    watched
+}
+
+/// Validates and bundles, and tells the pages how it went. Returns the
+/// configuration, unless it could not be loaded.
+fn rebuild(args: &Args, server: &Server) -> Option<Config> {
+    let config = match args.config() {
+        Ok(config) => config,
+        Err(e) => {
+            server.fail("Invalid configuration", vec![e.to_string()]);
+            return None;
+        }
+    };
+    *server.config.lock().unwrap() = config.clone();
+
+    println!("\n{}", "Validating question sets...".yellow());
+    match Validator::new(&config).and_then(|validator| validator.run()) {
+        Err(e) => {
+            server.fail("Validation failed", vec![e.to_string()]);
+            return Some(config);
+        }
+        Ok(validation) => {
+            let diagnostics: Vec<&Diagnostic> =
+                validation.reports.iter().flat_map(|r| r.diagnostics.iter()).collect();
+            if !validation.is_valid() {
+                let errors = diagnostics.iter().filter(|d| d.is_error()).map(|d| describe(d)).collect();
+                server.fail("Validation failed", errors);
+                return Some(config);
+            }
+            println!(
+                "  {} {} files valid, {} warning(s)",
+                "→".green(),
+                validation.files.len(),
+                diagnostics.len()
+            );
+            for diagnostic in diagnostics {
+                println!("    {} {}", "•".dimmed(), describe(diagnostic).yellow());
+            }
+        }
+    }
+
+    println!();
+    let build = server.status.lock().unwrap().build + 1;
+    let script = format!("const LIVE_RELOAD_BUILD = {};\n{}", build, CLIENT_SCRIPT);
+    match bundle::build(&config, Some(&script)) {
+        Ok(()) => server.publish(Status { build, errors: None }),
+        Err(e) => server.fail("Build failed", vec![e.to_string()]),
+    }
+    Some(config)
+}
+
+/// One finding on one line, compiler-style.
+fn describe(diagnostic: &Diagnostic) -> String {
+    let mut position = diagnostic.file.display().to_string();
+    if let Some(location) = &diagnostic.location {
+        position += &format!(":{}:{}", location.line, location.column);
+    }
+    let mut text = format!(
+        "{}: {}[{}]: {}",
+        position, diagnostic.severity, diagnostic.rule, diagnostic.message
+    );
+    if let Some(id) = &diagnostic.question_id {
+        text += &format!(" (question '{}')", id);
+    }
+    text
+}
+
+/// Answers one HTTP request: the event stream, or a file.
+async fn handle(mut stream: TcpStream, server: Arc<Server>) -> Result<()> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || head.len() > MAX_REQUEST {
+            return Ok(());
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let target = request_line.next().unwrap_or("/");
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain; charset=utf-8", b"Method not allowed\n", false)
+            .await;
+    }
+    let head_only = method == "HEAD";
+    if path == EVENTS_PATH {
+        return events(stream, server).await;
+    }
+
+    let (page, roots) = {
+        let config = server.config.lock().unwrap();
+        let page = config.paths.output.clone();
+        let page_dir = page.parent().unwrap_or(Path::new("")).to_path_buf();
+        (page, [page_dir, config.paths.asset_root.clone()])
+    };
+    match resolve(path, &page, &roots) {
+        Some(file) => {
+            let body = tokio::fs::read(&file).await?;
+            respond(&mut stream, "200 OK", content_type(&file), &body, head_only).await
+        }
+        None if path == "/" => {
+            // Nothing built yet: a blank page that shows why and reloads
+            // once the build succeeds
+            let build = server.status.lock().unwrap().build;
+            let body = format!(
+                "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Just Learn Just Game</title></head>\n<body>\n<script>\nconst LIVE_RELOAD_BUILD = {};\n{}</script>\n</body>\n</html>\n",
+                build, CLIENT_SCRIPT
+            );
+            respond(&mut stream, "200 OK", "text/html; charset=utf-8", body.as_bytes(), head_only).await
+        }
+        None => respond(&mut stream, "404 Not Found", "text/plain; charset=utf-8", b"Not found\n", head_only).await,
+    }
+}
+
+/// Finds the file for a request path: `/` is the page, anything else is
+/// looked up next to the page, then under the asset root.
+fn resolve(path: &str, page: &Path, roots: &[PathBuf]) -> Option<PathBuf> {
+    let path = percent_decode(path)?;
+    let mut relative = PathBuf::new();
+    for segment in path.split('/').filter(|s| !s.is_empty() && *s != ".") {
+        if segment == ".." || segment.contains('\\') {
+            return None;
+        }
+        relative.push(segment);
+    }
+    if relative.as_os_str().is_empty() {
+        return page.is_file().then(|| page.to_path_buf());
+    }
+    roots.iter().map(|root| root.join(&relative)).find(|file| file.is_file())
+}
+
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn content_type(file: &Path) -> &'static str {
+    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "html" => "text/html; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        _ => mime_type(&file.to_string_lossy()).unwrap_or("application/octet-stream"),
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8], head_only: bool) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    if !head_only {
+        stream.write_all(body).await?;
+    }
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Streams the build status to a page until it goes away.
+async fn events(mut stream: TcpStream, server: Arc<Server>) -> Result<()> {
+    let mut updates = server.updates.subscribe();
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\nretry: 1000\n\n",
+        )
+        .await?;
+    let status = server.status.lock().unwrap().clone();
+    send_status(&mut stream, &status).await?;
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(status) => send_status(&mut stream, &status).await?,
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let status = server.status.lock().unwrap().clone();
+                    send_status(&mut stream, &status).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = tokio::time::sleep(KEEP_ALIVE) => stream.write_all(b": keep-alive\n\n").await?,
+        }
+    }
+}
+
+async fn send_status(stream: &mut TcpStream, status: &Status) -> Result<()> {
+    let event = format!("event: status\ndata: {}\n\n", serde_json::to_string(status)?);
+    stream.write_all(event.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
@@ -39,7 +39,7 @@ async fn main() -> Result<()> {
         .context("Failed to launch browser")?;
 
     // Drive events in background
-    tokio::spawn(async move { while let Some(_) = handler.next().await {} });
+    tokio::spawn(async move { while handler.next().await.is_some() {} });
 
     // 3) Open page, capture console logs, click button
     let page = browser.new_page("about:blank").await?;
@@ -49,7 +49,7 @@ async fn main() -> Result<()> {
         .await?;
     tokio::spawn(async move {
         while let Some(event) = console_events.next().await {
-            if let Some(arg) = event.args.get(0) {
+            if let Some(arg) = event.args.first() {
                 if let Some(val) = &arg.value {
                     if let Some(text) = val.as_str() {
                         println!("LOG: {}", text);
@@ -1,24 +1,37 @@
 use anyhow::{anyhow, Context, Result};
 use std::env;
 use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::target::{CreateBrowserContextParams, CreateTargetParams};
+use chromiumoxide::cdp::browser_protocol::network::{EventLoadingFailed, EventRequestWillBeSent};
+use chromiumoxide::cdp::js_protocol::runtime::{ConsoleApiCalledType, EventConsoleApiCalled, EventExceptionThrown};
 use chromiumoxide::handler::viewport::Viewport;
 use chromiumoxide::page::Page;
 use chromiumoxide::page::ScreenshotParams;
 use clap::Parser;
 use colored::*;
-use futures::StreamExt;
-use serde::Deserialize;
-use std::path::Path;
-use std::time::Duration;
+use futures::{stream, StreamExt};
+use just_learn_just_build_tools::config::{Config, ConfigArgs};
+use just_learn_just_build_tools::test_report::{self, Status, Step, TestResult};
+use just_learn_just_build_tools::{load_question_sets, Metadata, Mode, Question};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Data-driven browser test runner for just-learn-just game", long_about = None)]
 struct Args {
     /// Run tests in headless mode
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_headless")]
     headless: bool,
 
+    /// Show the browser window, overriding game.toml
+    #[arg(long, overrides_with = "headless")]
+    no_headless: bool,
+
     /// Filter question sets by name pattern
     #[arg(long)]
     filter: Option<String>,
@@ -27,45 +40,142 @@ struct Args {
     #[arg(long, short = 'v')]
     verbose: bool,
 
-    /// Path to the HTML file to test
-    #[arg(long, default_value = "./index.html")]
-    html_path: String,
+    /// Path to the HTML file to test [default: index.html]
+    #[arg(long, value_name = "PATH")]
+    html_path: Option<PathBuf>,
 
-    /// Timeout for page operations in seconds
-    #[arg(long, default_value = "10")]
-    timeout: u64,
+    /// Timeout for page operations in seconds [default: 10]
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
 
     /// Run only one easy and one hard question set (first found of each)
     #[arg(long)]
     first_per_mode: bool,
+
+    /// Directory containing the questions*.json files [default: data]
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Where to write screenshots [default: test_output]
+    #[arg(long, value_name = "DIR")]
+    test_output: Option<PathBuf>,
+
+    /// Viewport to test with, by name from game.toml [default: desktop]
+    #[arg(long, value_name = "NAME")]
+    viewport: Option<String>,
+
+    /// Question sets to run at once, each in its own browser context
+    /// [default: 1]
+    #[arg(long, short = 'j', value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Run every question set, however many fail
+    #[arg(long, conflicts_with = "max_failures")]
+    no_fail_fast: bool,
+
+    /// Start no further question sets once this many have failed
+    /// [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_failures: Option<u64>,
+
+    /// Also write a report, as junit=PATH or json=PATH (repeatable), with
+    /// each set's steps and their timing, screenshots, console errors and
+    /// failure messages
+    #[arg(long, value_name = "FORMAT=PATH", value_parser = parse_report)]
+    report: Vec<Report>,
+
+    /// Don't fail on page errors containing TEXT (repeatable), in addition
+    /// to those in game.toml
+    #[arg(long, value_name = "TEXT")]
+    allow_error: Vec<String>,
+
+    #[command(flatten)]
+    config: ConfigArgs,
 }
 
-#[derive(Debug, Deserialize)]
-struct QuestionSet {
-    metadata: Metadata,
-    questions: Vec<Question>,
+impl Args {
+    /// The manifest with this run's flags applied on top.
+    fn config(&self) -> Result<Config> {
+        let mut config = self.config.load()?;
+        if let Some(page) = &self.html_path {
+            config.paths.output = page.clone();
+        }
+        if let Some(dir) = &self.data_dir {
+            config.paths.data_dir = dir.clone();
+        }
+        if let Some(dir) = &self.test_output {
+            config.paths.test_output = dir.clone();
+        }
+        if let Some(timeout) = self.timeout {
+            config.test.timeout = timeout;
+        }
+        if let Some(viewport) = &self.viewport {
+            config.test.viewport = viewport.clone();
+        }
+        if let Some(jobs) = self.jobs {
+            config.test.jobs = jobs;
+        }
+        if config.test.jobs == 0 {
+            anyhow::bail!("--jobs must be at least 1");
+        }
+        if let Some(max_failures) = self.max_failures {
+            config.test.max_failures = max_failures as usize;
+        }
+        if self.no_fail_fast {
+            config.test.max_failures = 0;
+        }
+        if self.headless || self.no_headless {
+            config.test.headless = self.headless;
+        }
+        config.test.allow_errors.extend(self.allow_error.iter().cloned());
+        // Fail early on a typo rather than after launching the browser
+        config.viewport(&config.test.viewport)?;
+        Ok(config)
+    }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct Metadata {
-    title: String,
-    description: String,
-    mode: String,
-    #[serde(rename = "targetAge")]
-    target_age: String,
-    subject: String,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportFormat {
+    Junit,
+    Json,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct Question {
-    id: String,
-    question: String,
-    choices: Vec<String>,
-    #[serde(rename = "correctAnswer")]
-    correct_answer: usize,
-    explanation: String,
+/// A report to write, from `--report FORMAT=PATH`.
+#[derive(Clone, Debug)]
+struct Report {
+    format: ReportFormat,
+    path: PathBuf,
+}
+
+fn parse_report(value: &str) -> std::result::Result<Report, String> {
+    let (format, path) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected FORMAT=PATH, e.g. junit=results.xml, got '{}'", value))?;
+    let format = match format {
+        "junit" => ReportFormat::Junit,
+        "json" => ReportFormat::Json,
+        _ => return Err(format!("unknown report format '{}' (known: junit, json)", format)),
+    };
+    if path.is_empty() {
+        return Err("the report path is empty".to_string());
+    }
+    Ok(Report {
+        format,
+        path: PathBuf::from(path),
+    })
+}
+
+impl Report {
+    fn write(&self, results: &[TestResult], elapsed: Duration) -> Result<()> {
+        let content = match self.format {
+            ReportFormat::Junit => test_report::to_junit(results, elapsed),
+            ReportFormat::Json => serde_json::to_string_pretty(&test_report::to_json(results, elapsed))? + "\n",
+        };
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&self.path, content).with_context(|| format!("Failed to write {}", self.path.display()))
+    }
 }
 
 #[derive(Debug)]
@@ -76,16 +186,127 @@ struct TestCase {
     questions: Vec<Question>,
 }
 
-#[derive(Debug, Clone)]
-struct TestResult {
-    name: String,
-    mode: String,
-    passed: bool,
-    error: Option<String>,
+/// What a test case records besides its outcome.
+#[derive(Debug, Default)]
+struct Record {
+    steps: Vec<Step>,
+    screenshots: Vec<PathBuf>,
+    console_errors: Vec<String>,
+}
+
+impl TestCase {
+    fn result(&self, status: Status, error: Option<String>, duration: Duration, record: Record) -> TestResult {
+        TestResult {
+            key: self.key.clone(),
+            name: self.metadata.title.clone(),
+            mode: self.metadata.mode.to_string(),
+            status,
+            error,
+            duration,
+            steps: record.steps,
+            screenshots: record.screenshots,
+            console_errors: record.console_errors,
+        }
+    }
+}
+
+/// Output of one test case. With one job it is printed as it happens; with
+/// more it is held back and printed in one piece when the case ends, so
+/// cases running side by side don't interleave.
+#[derive(Clone)]
+struct Log {
+    live: bool,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Log {
+    fn new(live: bool) -> Self {
+        Self {
+            live,
+            lines: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn line(&self, line: impl Into<String>) {
+        let line = line.into();
+        if self.live {
+            println!("{}", line);
+        } else {
+            self.lines.lock().unwrap().push(line);
+        }
+    }
+
+    fn flush(&self) {
+        for line in self.lines.lock().unwrap().drain(..) {
+            println!("{}", line);
+        }
+    }
+}
+
+/// The page a test case drives, with what it needs to report.
+struct Session<'a> {
+    page: Page,
+    /// Key of the question set, to tell its screenshots apart.
+    key: &'a str,
+    log: Log,
+    record: Arc<Mutex<Record>>,
+}
+
+impl Session<'_> {
+    /// Runs `work` as a step named `name`, recording how long it took and
+    /// whether it failed.
+    async fn step<T>(&self, name: impl Into<String>, work: impl Future<Output = Result<T>>) -> Result<T> {
+        let index = {
+            let mut record = self.record.lock().unwrap();
+            record.steps.push(Step {
+                name: name.into(),
+                duration: Duration::ZERO,
+                error: None,
+            });
+            record.steps.len() - 1
+        };
+        let start = Instant::now();
+        let outcome = work.await;
+        let step = &mut self.record.lock().unwrap().steps[index];
+        step.duration = start.elapsed();
+        step.error = outcome.as_ref().err().map(|e| format!("{:#}", e));
+        outcome
+    }
+
+    /// Renames the step being run, once it is known what it is about.
+    fn name_step(&self, name: String) {
+        if let Some(step) = self.record.lock().unwrap().steps.last_mut() {
+            step.name = name;
+        }
+    }
+}
+
+/// Where the page's error listeners send what they see.
+#[derive(Clone)]
+struct ErrorSink {
+    log: Log,
+    record: Arc<Mutex<Record>>,
+    /// Errors containing any of these are only echoed, with `--verbose`.
+    allow: Arc<Vec<String>>,
+    verbose: bool,
+}
+
+impl ErrorSink {
+    fn report(&self, message: String) {
+        let allowed = self.allow.iter().any(|a| message.contains(a.as_str()));
+        if self.verbose {
+            let note = if allowed { " (allowed)" } else { "" };
+            self.log.line(format!("    {} {}{}", "🌐".dimmed(), message.red(), note.dimmed()));
+        }
+        if !allowed {
+            self.record.lock().unwrap().console_errors.push(message);
+        }
+    }
 }
 
 struct GameTestRunner {
     args: Args,
+    config: Config,
     browser: Browser,
     test_cases: Vec<TestCase>,
 }
@@ -98,11 +319,14 @@ impl GameTestRunner {
             .collect()
     }
 
-    async fn try_screenshot(&self, page: &Page, name_prefix: &str) {
-        let _ = std::fs::create_dir_all("test_output");
-        if let Ok(bytes) = page.screenshot(ScreenshotParams::default()).await {
-            let filename = format!("test_output/{}.png", name_prefix);
-            let _ = std::fs::write(filename, bytes);
+    async fn try_screenshot(&self, session: &Session<'_>, name_prefix: &str) {
+        let dir = &self.config.paths.test_output;
+        let _ = std::fs::create_dir_all(dir);
+        if let Ok(bytes) = session.page.screenshot(ScreenshotParams::default()).await {
+            let path = dir.join(format!("{}.png", name_prefix));
+            if std::fs::write(&path, bytes).is_ok() {
+                session.record.lock().unwrap().screenshots.push(path);
+            }
         }
     }
 
@@ -111,30 +335,31 @@ impl GameTestRunner {
         serde_json::to_string(input).unwrap_or_else(|_| "\"\"".to_string())
     }
 
-    async fn new(args: Args) -> Result<Self> {
+    async fn new(args: Args, config: Config) -> Result<Self> {
         println!("{}", "🎮 Starting Game Tests".bright_blue().bold());
         println!();
 
         // Discover test cases
-        let test_cases = Self::discover_test_cases(&args)?;
+        let test_cases = Self::discover_test_cases(&args, &config)?;
         
         if test_cases.is_empty() {
             anyhow::bail!("No question sets found to test!");
         }
 
         // Setup browser
+        let viewport = config.viewport(&config.test.viewport)?;
         let mut builder = BrowserConfig::builder()
             .args(vec!["--no-sandbox", "--disable-setuid-sandbox"])
             .viewport(Some(Viewport {
-                width: 1280,
-                height: 800,
-                device_scale_factor: Some(1.0),
-                emulating_mobile: false,
+                width: viewport.width,
+                height: viewport.height,
+                device_scale_factor: Some(viewport.scale),
+                emulating_mobile: viewport.mobile,
                 is_landscape: false,
-                has_touch: false,
+                has_touch: viewport.mobile,
             }));
 
-        if !args.headless {
+        if !config.test.headless {
             builder = builder.with_head();
         }
 
@@ -159,56 +384,40 @@ impl GameTestRunner {
 
         // Spawn browser handler
         tokio::spawn(async move {
-            while let Some(_) = handler.next().await {}
+            while handler.next().await.is_some() {}
         });
 
         Ok(Self {
             args,
+            config,
             browser,
             test_cases,
         })
     }
 
-    fn discover_test_cases(args: &Args) -> Result<Vec<TestCase>> {
-        let data_dir = Path::new("data");
+    fn discover_test_cases(args: &Args, config: &Config) -> Result<Vec<TestCase>> {
+        let data_dir = config.paths.data_dir.as_path();
         if !data_dir.exists() {
-            anyhow::bail!("data/ directory not found");
+            anyhow::bail!("{} directory not found", data_dir.display());
         }
 
         let mut test_cases = Vec::new();
 
         // Find all questions*.json files
-        for entry in std::fs::read_dir(data_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if let Some(filename) = path.file_name().and_then(|f| f.to_str()) {
-                if filename.starts_with("questions") && filename.ends_with(".json") {
-                    // Extract key (filename without extension)
-                    let key = filename.trim_end_matches(".json");
-                    
-                    // Apply filter if provided
-                    if let Some(filter) = &args.filter {
-                        if !key.contains(filter) {
-                            continue;
-                        }
-                    }
-
-                    // Load and parse JSON
-                    let content = std::fs::read_to_string(&path)
-                        .with_context(|| format!("Failed to read {}", path.display()))?;
-                    
-                    let question_set: QuestionSet = serde_json::from_str(&content)
-                        .with_context(|| format!("Failed to parse {}", path.display()))?;
-
-                    test_cases.push(TestCase {
-                        filename: filename.to_string(),
-                        key: key.to_string(),
-                        metadata: question_set.metadata,
-                        questions: question_set.questions,
-                    });
+        for file in load_question_sets(data_dir)? {
+            // Apply filter if provided
+            if let Some(filter) = &args.filter {
+                if !file.key.contains(filter) {
+                    continue;
                 }
             }
+
+            test_cases.push(TestCase {
+                filename: file.filename().to_string(),
+                key: file.key,
+                metadata: file.set.metadata,
+                questions: file.set.questions,
+            });
         }
 
         // Sort test cases for consistent ordering
@@ -222,10 +431,10 @@ impl GameTestRunner {
                 "•".dimmed(),
                 tc.key.bright_white(),
                 tc.metadata.title,
-                if tc.metadata.mode == "easy" {
-                    tc.metadata.mode.green()
+                if tc.metadata.mode == Mode::Easy {
+                    tc.metadata.mode.as_str().green()
                 } else {
-                    tc.metadata.mode.yellow()
+                    tc.metadata.mode.as_str().yellow()
                 }
             );
         }
@@ -242,9 +451,9 @@ impl GameTestRunner {
             let mut easy: Option<&TestCase> = None;
             let mut hard: Option<&TestCase> = None;
             for tc in &self.test_cases {
-                match tc.metadata.mode.as_str() {
-                    "easy" if easy.is_none() => easy = Some(tc),
-                    "hard" if hard.is_none() => hard = Some(tc),
+                match tc.metadata.mode {
+                    Mode::Easy if easy.is_none() => easy = Some(tc),
+                    Mode::Hard if hard.is_none() => hard = Some(tc),
                     _ => {}
                 }
                 if easy.is_some() && hard.is_some() { break; }
@@ -254,214 +463,454 @@ impl GameTestRunner {
             self.test_cases.iter().collect()
         };
 
-        for test_case in selected_cases {
-            let result = self.run_test_case(test_case).await;
-            let passed = result.passed;
+        // Start no further cases once max_failures have failed (0: no limit).
+        // Cases already running finish either way
+        let failures = AtomicUsize::new(0);
+        let (jobs, max_failures) = (self.config.test.jobs, self.config.test.max_failures);
+        let mut runs = stream::iter(selected_cases)
+            .map(|test_case| {
+                let failures = &failures;
+                async move {
+                    if max_failures > 0 && failures.load(Ordering::SeqCst) >= max_failures {
+                        return test_case.result(Status::NotRun, None, Duration::ZERO, Record::default());
+                    }
+                    let result = self.run_test_case(test_case, Log::new(jobs == 1)).await;
+                    if result.status == Status::Failed {
+                        failures.fetch_add(1, Ordering::SeqCst);
+                    }
+                    result
+                }
+            })
+            .buffered(jobs);
+        while let Some(result) = runs.next().await {
             results.push(result);
-            // Fail fast: stop after first failure
-            if !passed { break; }
         }
 
         results
     }
 
-    async fn run_test_case(&self, test_case: &TestCase) -> TestResult {
-        println!(
+    async fn run_test_case(&self, test_case: &TestCase, log: Log) -> TestResult {
+        log.line(format!(
             "{} Testing: {} ({})",
             "🧪".bright_blue(),
             test_case.metadata.title.bright_white(),
             test_case.metadata.mode
-        );
+        ));
 
-        match self.test_question_set(test_case).await {
+        let start = Instant::now();
+        let (outcome, record) = self.test_question_set(test_case, &log).await;
+        let duration = start.elapsed();
+        let result = match outcome {
             Ok(_) => {
-                println!("  {} Passed\n", "✅".green());
-                TestResult {
-                    name: test_case.metadata.title.clone(),
-                    mode: test_case.metadata.mode.clone(),
-                    passed: true,
-                    error: None,
-                }
+                log.line(format!("  {} Passed in {:.1}s\n", "✅".green(), duration.as_secs_f64()));
+                test_case.result(Status::Passed, None, duration, record)
             }
             Err(e) => {
-                println!("  {} Failed: {}\n", "❌".red(), e);
-                TestResult {
-                    name: test_case.metadata.title.clone(),
-                    mode: test_case.metadata.mode.clone(),
-                    passed: false,
-                    error: Some(e.to_string()),
-                }
+                log.line(format!("  {} Failed after {:.1}s: {}\n", "❌".red(), duration.as_secs_f64(), e));
+                test_case.result(Status::Failed, Some(e.to_string()), duration, record)
             }
-        }
+        };
+        log.flush();
+        result
     }
 
-    async fn test_question_set(&self, test_case: &TestCase) -> Result<()> {
-        // Create new page for this test
-        let page = self.browser.new_page("about:blank").await?;
-        
-        // Set up console listener if verbose
-        if self.args.verbose {
-            let mut console_events = page.event_listener::<chromiumoxide::cdp::js_protocol::runtime::EventConsoleApiCalled>().await?;
-            
-            tokio::spawn(async move {
-                while let Some(event) = console_events.next().await {
-                    if let Some(args) = event.args.get(0) {
-                        if let Some(value) = &args.value {
-                            if let Some(text) = value.as_str() {
-                                println!("    {} {}", "🌐".dimmed(), text.dimmed());
-                            }
-                        }
-                    }
-                }
-            });
-        }
-
-        // Navigate to game
-        let html_path = std::fs::canonicalize(&self.args.html_path)
-            .context("Failed to resolve HTML path")?;
-        let file_url = format!("file://{}", html_path.display());
-        
-        page.goto(&file_url)
+    /// Plays `test_case` in a fresh browser context, so no state such as
+    /// localStorage carries over from other cases, whether they ran before
+    /// or are running alongside. Returns what was recorded even when the
+    /// case fails.
+    async fn test_question_set(&self, test_case: &TestCase, log: &Log) -> (Result<()>, Record) {
+        let record = Arc::new(Mutex::new(Record::default()));
+        let context = match self
+            .browser
+            .create_browser_context(CreateBrowserContextParams::default())
             .await
-            .context("Failed to navigate to game")?;
+        {
+            Ok(context) => context,
+            Err(e) => return (Err(anyhow!("Failed to create browser context: {}", e)), Record::default()),
+        };
+        let target = CreateTargetParams::builder()
+            .url("about:blank")
+            .browser_context_id(context.clone())
+            .build()
+            .map_err(|e| anyhow!(e));
+        let outcome = match target {
+            Ok(target) => match self.browser.new_page(target).await {
+                Ok(page) => {
+                    let session = Session {
+                        page,
+                        key: &test_case.key,
+                        log: log.clone(),
+                        record: record.clone(),
+                    };
+                    self.play(&session, test_case).await
+                }
+                Err(e) => Err(anyhow!("Failed to open page: {}", e)),
+            },
+            Err(e) => Err(e),
+        };
+        self.browser.dispose_browser_context(context).await.ok();
+        let record = std::mem::take(&mut *record.lock().unwrap());
+        (outcome, record)
+    }
 
-        // Wait for start screen
-        self.wait_for_element(&page, "#start-screen").await?;
-        
-        // Verify start screen is visible
-        let start_visible = page
-            .evaluate("document.getElementById('start-screen').style.display !== 'none'")
-            .await?
-            .into_value::<bool>()?;
-        
-        if !start_visible {
-            self.try_screenshot(&page, "fail_start_not_visible").await;
-            anyhow::bail!("Start screen not visible");
-        }
+    /// Records what the page reports going wrong, which fails the test case
+    /// unless it matches `test.allow-errors`: console errors, uncaught
+    /// exceptions and resources that failed to load, such as a missing
+    /// image. With `--verbose` all console output is echoed too.
+    async fn watch_page_errors(&self, session: &Session<'_>) -> Result<()> {
+        let page = &session.page;
+        let sink = ErrorSink {
+            log: session.log.clone(),
+            record: session.record.clone(),
+            allow: Arc::new(self.config.test.allow_errors.clone()),
+            verbose: self.args.verbose,
+        };
 
-        println!("    Testing start screen... ✓");
+        let mut console_events = page.event_listener::<EventConsoleApiCalled>().await?;
+        let console = sink.clone();
+        tokio::spawn(async move {
+            while let Some(event) = console_events.next().await {
+                let text = event
+                    .args
+                    .iter()
+                    .map(|arg| match &arg.value {
+                        Some(serde_json::Value::String(text)) => text.clone(),
+                        Some(value) => value.to_string(),
+                        None => arg.description.clone().unwrap_or_default(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if event.r#type == ConsoleApiCalledType::Error {
+                    console.report(format!("console.error: {}", text));
+                } else if console.verbose {
+                    console.log.line(format!("    {} {}", "🌐".dimmed(), text.dimmed()));
+                }
+            }
+        });
 
-        // Click the tile for this question set
-        let tile_selector = format!("[data-key='{}']", test_case.key);
-        // Ensure tiles are rendered before clicking
-        self.wait_for_element(&page, ".question-set-tile").await?;
-        // Debug: list available tiles and take a screenshot of the start screen
-        if let Ok(eval) = page
-            .evaluate("Array.from(document.querySelectorAll('.question-set-tile')).map(el => el.dataset.key)")
-            .await
-        {
-            if let Ok(keys) = eval.into_value::<Vec<String>>() {
-                println!("    Available tiles: {:?}", keys);
+        let mut exceptions = page.event_listener::<EventExceptionThrown>().await?;
+        let uncaught = sink.clone();
+        tokio::spawn(async move {
+            while let Some(event) = exceptions.next().await {
+                let details = &event.exception_details;
+                let message = details
+                    .exception
+                    .as_ref()
+                    .and_then(|e| e.description.clone())
+                    .unwrap_or_else(|| details.text.clone());
+                let location = match &details.url {
+                    Some(url) => format!(" ({}:{})", url, details.line_number + 1),
+                    None => String::new(),
+                };
+                uncaught.report(format!("Uncaught exception: {}{}", message, location));
             }
-        }
-        self.try_screenshot(&page, &format!("tiles_present_before_click_{}", test_case.key)).await;
-        self.wait_for_element(&page, &tile_selector).await?;
-        // Before click, take a screenshot for debugging
-        let _ = std::fs::create_dir_all("test_output");
-        if let Ok(bytes) = page.screenshot(ScreenshotParams::default()).await {
-            let _ = std::fs::write(format!("test_output/before_click_{}.png", test_case.key), bytes);
-        }
-        self.click_element(&page, &tile_selector).await
-            .with_context(|| format!("Failed to find tile with data-key='{}'", test_case.key))?;
+        });
 
-        // Wait for game screen
-        self.wait_for_element(&page, "#game-screen").await?;
-        
-        // Test questions per mode
-        let total_questions = test_case.questions.len();
-        if test_case.metadata.mode == "hard" {
-            for question_num in 1..=total_questions {
-                // Wait for choice buttons
-                self.wait_for_element(&page, ".choice-button").await?;
-                // Always click the first choice
-                self.click_element(&page, ".choice-button:nth-child(1)").await?;
-                // Wait for result screen and click next
-                self.wait_for_element(&page, "#result-screen").await?;
-                self.click_element(&page, "#next-button").await?;
-                println!("    [hard] Testing question {}/{}... ✓", question_num, total_questions);
+        // Failed loads only carry the request id, so remember each request's URL
+        let urls: Arc<Mutex<HashMap<String, String>>> = Arc::default();
+        let mut requests = page.event_listener::<EventRequestWillBeSent>().await?;
+        let sent = urls.clone();
+        tokio::spawn(async move {
+            while let Some(event) = requests.next().await {
+                sent.lock()
+                    .unwrap()
+                    .insert(event.request_id.inner().clone(), event.request.url.clone());
             }
-        } else {
-            for (i, question) in test_case.questions.iter().enumerate() {
-                let question_num = i + 1;
-                // Wait for choice buttons
-                self.wait_for_element(&page, ".choice-button").await?;
-
-                if question_num == 1 {
-                    // First do a wrong answer attempt (stay on game screen), then correct
-                    let wrong_index = if question.correct_answer == 0 { 1 } else { 0 };
-                    let wrong_selector = format!(".choice-button:nth-child({})", wrong_index + 1);
-                    self.click_element(&page, &wrong_selector).await?;
-                    // Small pause, still on game screen
-                    sleep(Duration::from_millis(500)).await;
-                    // Now answer correctly
-                    self.click_correct_answer(&page, question).await?;
-                } else {
-                    // For remaining questions, answer correctly directly
-                    self.click_correct_answer(&page, question).await?;
+        });
+        let mut failed_loads = page.event_listener::<EventLoadingFailed>().await?;
+        tokio::spawn(async move {
+            while let Some(event) = failed_loads.next().await {
+                if event.canceled == Some(true) {
+                    continue;
                 }
-
-                // Wait for result screen and click next, unless it's the final question where next leads to finish
-                self.wait_for_element(&page, "#result-screen").await?;
-                self.click_element(&page, "#next-button").await?;
-                println!("    [easy] Testing question {}/{}... ✓", question_num, total_questions);
+                let url = urls
+                    .lock()
+                    .unwrap()
+                    .get(event.request_id.inner())
+                    .cloned()
+                    .unwrap_or_else(|| "a resource".to_string());
+                sink.report(format!("Failed to load {}: {}", url, event.error_text));
             }
-        }
+        });
+        Ok(())
+    }
+
+    async fn play(&self, session: &Session<'_>, test_case: &TestCase) -> Result<()> {
+        let page = &session.page;
+
+        self.watch_page_errors(session).await?;
 
-        // Should be on finish screen
-        self.wait_for_element(&page, "#finish-screen").await?;
-        // After finishing, take a screenshot
-        if let Ok(bytes) = page.screenshot(ScreenshotParams::default()).await {
-            let _ = std::fs::write(format!("test_output/finish_{}.png", test_case.key), bytes);
+        session
+            .step("load page", async {
+                // Navigate to game
+                let html_path = std::fs::canonicalize(&self.config.paths.output)
+                    .context("Failed to resolve HTML path")?;
+                let file_url = format!("file://{}", html_path.display());
+
+                page.goto(&file_url)
+                    .await
+                    .context("Failed to navigate to game")?;
+
+                // Wait for start screen
+                self.wait_for_element(session, "#start-screen").await?;
+
+                // Verify start screen is visible
+                let start_visible = page
+                    .evaluate("document.getElementById('start-screen').style.display !== 'none'")
+                    .await?
+                    .into_value::<bool>()?;
+
+                if !start_visible {
+                    self.try_screenshot(session, &format!("fail_start_not_visible_{}", test_case.key)).await;
+                    anyhow::bail!("Start screen not visible");
+                }
+                Ok(())
+            })
+            .await?;
+
+        session.log.line("    Testing start screen... ✓");
+
+        session
+            .step("choose question set", async {
+                // Click the tile for this question set
+                let tile_selector = format!("[data-key='{}']", test_case.key);
+                // Ensure tiles are rendered before clicking
+                self.wait_for_element(session, ".question-set-tile").await?;
+                // Debug: list available tiles and take a screenshot of the start screen
+                if let Ok(eval) = page
+                    .evaluate("Array.from(document.querySelectorAll('.question-set-tile')).map(el => el.dataset.key)")
+                    .await
+                {
+                    if let Ok(keys) = eval.into_value::<Vec<String>>() {
+                        session.log.line(format!("    Available tiles: {:?}", keys));
+                    }
+                }
+                self.try_screenshot(session, &format!("tiles_present_before_click_{}", test_case.key)).await;
+                self.wait_for_element(session, &tile_selector).await?;
+                // Before click, take a screenshot for debugging
+                self.try_screenshot(session, &format!("before_click_{}", test_case.key)).await;
+                self.click_element(session, &tile_selector).await
+                    .with_context(|| format!("Failed to find tile with data-key='{}'", test_case.key))?;
+
+                // Wait for game screen
+                self.wait_for_element(session, "#game-screen").await
+            })
+            .await?;
+        
+        // Test questions per mode, counting the right answers for the
+        // final score
+        let total_questions = test_case.questions.len();
+        let mut score = 0;
+        for question_num in 1..=total_questions {
+            let step = format!("question {}/{}", question_num, total_questions);
+            let correct = session
+                .step(step.clone(), async {
+                    // Wait for choice buttons
+                    self.wait_for_game_state(session, "playing").await?;
+                    self.wait_for_element(session, ".choice-button").await?;
+                    // The game shuffles the questions, so answer the one shown
+                    let question = self.current_question(session).await?;
+                    session.name_step(format!("{} ({})", step, question.id));
+
+                    let correct = if test_case.metadata.mode == Mode::Hard {
+                        // Always click the first choice
+                        self.click_element(session, ".choice-button:nth-child(1)").await?;
+                        question.correct_answer == 0
+                    } else {
+                        if question_num == 1 {
+                            // First do a wrong answer attempt (stay on game screen), then correct
+                            let wrong_index = if question.correct_answer == 0 { 1 } else { 0 };
+                            let wrong_selector = format!(".choice-button:nth-child({})", wrong_index + 1);
+                            self.click_element(session, &wrong_selector).await?;
+                            // Small pause, still on game screen
+                            sleep(Duration::from_millis(500)).await;
+                        }
+                        // Answer correctly; the engine doesn't count wrong attempts in easy mode
+                        self.click_correct_answer(session, &question).await?;
+                        true
+                    };
+
+                    // Wait for the engine to take the answer and the result
+                    // screen to appear, which the page delays, then move on
+                    self.wait_for_game_state(session, "question-answered").await?;
+                    self.wait_for_element(session, "#result-screen").await?;
+                    self.wait_for_element(session, "#next-button").await?;
+                    self.click_element(session, "#next-button").await?;
+                    Ok(correct)
+                })
+                .await?;
+            score += usize::from(correct);
+            session.log.line(format!(
+                "    [{}] Testing question {}/{}... ✓",
+                test_case.metadata.mode, question_num, total_questions
+            ));
         }
+
+        session
+            .step("finish screen", async {
+                // Should be on finish screen
+                self.wait_for_element(session, "#finish-screen").await?;
+                // After finishing, take a screenshot
+                self.try_screenshot(session, &format!("finish_{}", test_case.key)).await;
+                Ok(())
+            })
+            .await?;
+
+        session
+            .step("final score", self.check_final_score(session, score, total_questions))
+            .await?;
+
+        session
+            .step("no page errors", async {
+                // Let the listeners catch up with the last events
+                sleep(Duration::from_millis(200)).await;
+                let errors = session.record.lock().unwrap().console_errors.clone();
+                match errors.as_slice() {
+                    [] => Ok(()),
+                    [error] => Err(anyhow!("The page reported an error: {}", error)),
+                    [first, ..] => Err(anyhow!(
+                        "The page reported {} errors, the first: {}",
+                        errors.len(),
+                        first
+                    )),
+                }
+            })
+            .await?;
         
         // Note: Skipping Play Again (reload) to avoid invalidating devtools context
 
         // Close page
-        page.close().await?;
+        page.clone().close().await?;
 
         Ok(())
     }
 
     
 
-    async fn click_correct_answer(&self, page: &Page, question: &Question) -> Result<()> {
+    /// Compares the finish screen, and the engine's own statistics, with
+    /// the `score` out of `total` that the answers played should have got.
+    async fn check_final_score(&self, session: &Session<'_>, score: usize, total: usize) -> Result<()> {
+        let shown: serde_json::Value = session
+            .page
+            .evaluate(
+                "({
+                    score: document.getElementById('final-score')?.textContent.trim(),
+                    percentage: document.getElementById('final-percentage')?.textContent.trim(),
+                    stats: window.gameEngine ? window.gameEngine.getStats() : null,
+                    strings: window.STRINGS || {}
+                })",
+            )
+            .await
+            .context("Failed to read the finish screen")?
+            .into_value()
+            .context("Failed to read the finish screen")?;
+
+        // As the page rounds it, with Math.round
+        let percentage = if total > 0 { (200 * score + total) / (2 * total) } else { 0 };
+        let mut mismatches = Vec::new();
+        let mut expect = |what: &str, actual: &serde_json::Value, expected: serde_json::Value| {
+            if *actual != expected {
+                mismatches.push(format!("{} is {}, expected {}", what, actual, expected));
+            }
+        };
+        // In the page's language, as ui.js fills in its strings
+        let text = |key: &str, default: &str, values: &[(&str, usize)]| -> serde_json::Value {
+            let template = shown["strings"][key].as_str().unwrap_or(default);
+            values
+                .iter()
+                .fold(template.to_string(), |text, (name, value)| {
+                    text.replace(&format!("{{{}}}", name), &value.to_string())
+                })
+                .into()
+        };
+        let expected_score = text("finish.score-value", "{score} out of {total}", &[("score", score), ("total", total)]);
+        let expected_percentage = text("finish.percentage-value", "{percentage}%", &[("percentage", percentage)]);
+        expect("#final-score", &shown["score"], expected_score);
+        expect("#final-percentage", &shown["percentage"], expected_percentage);
+        let stats = &shown["stats"];
+        if stats.is_null() {
+            mismatches.push("window.gameEngine is not set, so its statistics can't be checked".to_string());
+        } else {
+            expect("getStats().score", &stats["score"], score.into());
+            expect("getStats().answered", &stats["answered"], total.into());
+            expect("getStats().total", &stats["total"], total.into());
+            expect("getStats().percentage", &stats["percentage"], percentage.into());
+            expect("getStats().remaining", &stats["remaining"], 0.into());
+            expect("getStats().gameState", &stats["gameState"], "finished".into());
+        }
+
+        if mismatches.is_empty() {
+            session.log.line(format!("    Final score {} out of {} ({}%) ✓", score, total, percentage));
+            Ok(())
+        } else {
+            anyhow::bail!("Wrong final score: {}", mismatches.join("; "))
+        }
+    }
+
+    /// The question the game is showing.
+    async fn current_question(&self, session: &Session<'_>) -> Result<Question> {
+        session
+            .page
+            .evaluate("window.gameEngine.getCurrentQuestion()")
+            .await
+            .context("Failed to read the current question")?
+            .into_value()
+            .context("Failed to read the current question")
+    }
+
+    async fn click_correct_answer(&self, session: &Session<'_>, question: &Question) -> Result<()> {
         let correct_selector = format!(".choice-button:nth-child({})", question.correct_answer + 1);
-        self.click_element(page, &correct_selector).await
+        self.click_element(session, &correct_selector).await
+    }
+
+    /// Waits until `selector` matches an element that is shown. Hidden
+    /// screens stay in the DOM, so being there is not enough.
+    async fn wait_for_element(&self, session: &Session<'_>, selector: &str) -> Result<()> {
+        let condition = format!(
+            "(el => el !== null && getComputedStyle(el).display !== 'none' && el.getClientRects().length > 0)(document.querySelector({}))",
+            Self::js_string_literal(selector)
+        );
+        self.wait_until(session, &format!("element: {}", selector), selector, &condition).await
+    }
+
+    /// Waits until the game engine is in `state`, e.g. `question-answered`.
+    async fn wait_for_game_state(&self, session: &Session<'_>, state: &str) -> Result<()> {
+        let condition = format!(
+            "window.gameEngine !== undefined && window.gameEngine.gameState === {}",
+            Self::js_string_literal(state)
+        );
+        self.wait_until(session, &format!("game state: {}", state), state, &condition).await
     }
 
-    async fn wait_for_element(&self, page: &Page, selector: &str) -> Result<()> {
-        let timeout = Duration::from_secs(self.args.timeout);
+    /// Polls the JavaScript expression `condition` until it is true, or
+    /// fails with a screenshot after the timeout.
+    async fn wait_until(&self, session: &Session<'_>, what: &str, tag: &str, condition: &str) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.test.timeout);
         let start = std::time::Instant::now();
         
         loop {
-            let exists = match page
-                .evaluate(format!(
-                    "document.querySelector({}) !== null",
-                    Self::js_string_literal(selector)
-                ))
-                .await
-            {
+            let met = match session.page.evaluate(condition).await {
                 Ok(eval) => eval.into_value::<bool>().unwrap_or(false),
                 Err(_) => false,
             };
             
-            if exists {
+            if met {
                 return Ok(());
             }
             
             if start.elapsed() > timeout {
-                let safe = Self::sanitize_for_filename(selector);
-                let name = format!("fail_timeout_wait_for_{}", safe);
-                self.try_screenshot(page, &name).await;
-                anyhow::bail!("Timeout waiting for element: {}", selector);
+                let safe = Self::sanitize_for_filename(tag);
+                let name = format!("fail_timeout_wait_for_{}_{}", safe, session.key);
+                self.try_screenshot(session, &name).await;
+                anyhow::bail!("Timeout waiting for {}", what);
             }
             
             sleep(Duration::from_millis(100)).await;
         }
     }
 
-    async fn click_element(&self, page: &Page, selector: &str) -> Result<()> {
-        page.evaluate(format!(
+    async fn click_element(&self, session: &Session<'_>, selector: &str) -> Result<()> {
+        session.page.evaluate(format!(
             "document.querySelector({}).click()",
             Self::js_string_literal(selector)
         ))
@@ -470,39 +919,68 @@ impl GameTestRunner {
         Ok(())
     }
 
-    fn print_summary(&self, results: &[TestResult]) {
+    fn print_summary(&self, results: &[TestResult], elapsed: Duration) {
         println!("{}", "📊 Test Summary".bright_blue().bold());
         println!("{}", "================================".dimmed());
         
         for result in results {
-            if result.passed {
+            if result.status == Status::NotRun {
                 println!(
                     "{} {} {} ({})",
+                    "⏭️".yellow(),
+                    "NOT RUN".yellow().bold(),
+                    result.name,
+                    result.mode
+                );
+            } else if result.status == Status::Passed {
+                println!(
+                    "{} {} {} ({}, {:.1}s)",
                     "✅".green(),
                     "PASSED".green().bold(),
                     result.name,
-                    result.mode
+                    result.mode,
+                    result.duration.as_secs_f64()
                 );
             } else {
                 println!(
-                    "{} {} {} ({})",
+                    "{} {} {} ({}, {:.1}s)",
                     "❌".red(),
                     "FAILED".red().bold(),
                     result.name,
-                    result.mode
+                    result.mode,
+                    result.duration.as_secs_f64()
                 );
                 if let Some(error) = &result.error {
                     println!("    {}", error.red());
                 }
+                for error in &result.console_errors {
+                    println!("    {} {}", "🌐".dimmed(), error.dimmed());
+                }
             }
         }
         
         println!();
         
-        let passed = results.iter().filter(|r| r.passed).count();
+        let count = |status| results.iter().filter(|r| r.status == status).count();
+        let (passed, not_run) = (count(Status::Passed), count(Status::NotRun));
         let total = results.len();
         
         println!("Results: {}/{} question sets passed", passed, total);
+        if not_run > 0 {
+            println!(
+                "{} {} question set(s) not run after {} failure(s); use --no-fail-fast to run them all",
+                "⏭️".yellow(),
+                not_run,
+                count(Status::Failed)
+            );
+        }
+        let busy: Duration = results.iter().map(|r| r.duration).sum();
+        println!(
+            "Time: {:.1}s wall clock, {:.1}s across sets, {} job(s)",
+            elapsed.as_secs_f64(),
+            busy.as_secs_f64(),
+            self.config.test.jobs
+        );
         
         if passed == total {
             println!("{} Success! All tests passed!", "🎉".green());
@@ -515,14 +993,30 @@ impl GameTestRunner {
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    let config = args.config()?;
+    if args.config.print(&config)? {
+        return Ok(());
+    }
     
-    let mut runner = GameTestRunner::new(args).await?;
+    let mut runner = GameTestRunner::new(args, config).await?;
+    let start = Instant::now();
     let results = runner.run_all_tests().await;
-    runner.print_summary(&results);
+    let elapsed = start.elapsed();
+    runner.print_summary(&results, elapsed);
+    let mut reports_written = true;
+    for report in &runner.args.report {
+        match report.write(&results, elapsed) {
+            Ok(()) => println!("{} Report written to {}", "📄".dimmed(), report.path.display()),
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                reports_written = false;
+            }
+        }
+    }
     
     // Return appropriate exit code
-    let all_passed = results.iter().all(|r| r.passed);
+    let all_passed = results.iter().all(|r| r.status == Status::Passed);
     // Close browser before exiting to avoid background task lingering
     runner.browser.close().await.ok();
-    std::process::exit(if all_passed { 0 } else { 1 });
+    std::process::exit(if all_passed && reports_written { 0 } else { 1 });
 }
\ No newline at end of file
@@ -1,134 +1,327 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
+use chrono::Local;
+use clap::{Parser, ValueEnum};
 use colored::*;
-use jsonschema::JSONSchema;
-use serde_json::Value;
-use std::fs;
-use std::path::Path;
+use just_learn_just_build_tools::config::{Config, ConfigArgs, Level};
+use just_learn_just_build_tools::is_question_file;
+use just_learn_just_build_tools::report;
+use just_learn_just_build_tools::source_map::code_frame;
+use just_learn_just_build_tools::validation::{Diagnostic, Severity};
+use just_learn_just_build_tools::validator::{FileResult, Validation, Validator};
+use just_learn_just_build_tools::watch::Watcher;
+use std::path::{Path, PathBuf};
 use std::process;
-use walkdir::WalkDir;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Validates question sets against the schema and semantic rules", long_about = None)]
+struct Args {
+    /// Output format. Machine-readable formats are written to stdout
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Also check predator/prey questions against the food web graph
+    #[arg(long, overrides_with = "no_check_foodweb")]
+    check_foodweb: bool,
+
+    /// Skip the food web checks, overriding game.toml
+    #[arg(long, overrides_with = "check_foodweb")]
+    no_check_foodweb: bool,
+
+    /// Where to read the food web from (markdown with a mermaid block, .mmd or .json)
+    #[arg(long, value_name = "PATH")]
+    foodweb: Option<PathBuf>,
+
+    /// Directory containing the questions*.json files
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// JSON Schema for question sets [default: <data-dir>/schema.json]
+    #[arg(long, value_name = "PATH")]
+    schema: Option<PathBuf>,
+
+    /// Drop findings of a rule (repeatable)
+    #[arg(long, value_name = "RULE")]
+    allow: Vec<String>,
+
+    /// Report a rule's findings as warnings (repeatable)
+    #[arg(long, value_name = "RULE")]
+    warn: Vec<String>,
+
+    /// Report a rule's findings as errors (repeatable)
+    #[arg(long, value_name = "RULE")]
+    deny: Vec<String>,
+
+    /// Keep running and re-validate question files as they change (all of
+    /// them when the schema changes). Text format only
+    #[arg(long)]
+    watch: bool,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+impl Args {
+    /// The manifest with this run's flags applied on top.
+    fn config(&self) -> Result<Config> {
+        let mut config = self.config.load()?;
+        if let Some(dir) = &self.data_dir {
+            config.paths.data_dir = dir.clone();
+        }
+        if let Some(schema) = &self.schema {
+            config.paths.schema = Some(schema.clone());
+        }
+        if let Some(foodweb) = &self.foodweb {
+            config.paths.foodweb = foodweb.clone();
+        }
+        if self.check_foodweb || self.no_check_foodweb {
+            config.lint.check_foodweb = self.check_foodweb;
+        }
+        for (rules, level) in [(&self.allow, Level::Allow), (&self.warn, Level::Warn), (&self.deny, Level::Error)] {
+            for rule in rules {
+                config.lint.set(rule, level)?;
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Colored, human-readable output with code frames
+    Text,
+    /// JSON document listing every file and diagnostic
+    Json,
+    /// JUnit XML, one test case per file
+    Junit,
+    /// SARIF 2.1.0 for code-scanning annotations
+    Sarif,
+}
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("{} {}", "Error:".red().bold(), e);
-        process::exit(1);
+    let args = Args::parse();
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            process::exit(1);
+        }
     }
 }
 
-fn run() -> Result<()> {
-    println!("{}", "🔍 Just Learn Just Game - Schema Validator".blue().bold());
-    println!("{}", "=".repeat(50).dimmed());
+/// Validates every question file and prints the results in the requested
+/// format. Returns whether all files are valid.
+fn run(args: &Args) -> Result<bool> {
+    let config = args.config()?;
+    if args.config.print(&config)? {
+        return Ok(true);
+    }
+    if args.watch && args.format != Format::Text {
+        anyhow::bail!("--watch only works with --format text");
+    }
+
+    let validator = Validator::new(&config)?;
+    let validation = validator.run()?;
+    let reports = &validation.reports;
 
-    let data_dir = Path::new("data");
-    if !data_dir.exists() {
-        anyhow::bail!("Data directory not found at: {}", data_dir.display());
+    match args.format {
+        Format::Text => print_text(&validator.schema_path, &validation),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report::to_json(reports))?),
+        Format::Junit => print!("{}", report::to_junit(reports)),
+        Format::Sarif => println!("{}", serde_json::to_string_pretty(&report::to_sarif(reports))?),
     }
 
-    // Load schema
-    let schema_path = data_dir.join("schema.json");
-    let schema_content = fs::read_to_string(&schema_path)
-        .context(format!("Failed to read schema at {}", schema_path.display()))?;
-    
-    // Parse schema once to avoid lifetime issues
-    let schema: &'static Value = Box::leak(Box::new(
-        serde_json::from_str(&schema_content)
-            .context("Failed to parse schema.json")?
-    ));
-    
-    let compiled = JSONSchema::compile(schema)
-        .context("Failed to compile JSON schema")?;
-    
-    println!("✅ Schema loaded: {}\n", schema_path.display());
+    if args.watch {
+        watch(&config, validator, validation);
+    }
+    Ok(validation.is_valid())
+}
 
-    // Find and validate all question files
-    let mut results = Vec::new();
-    let mut file_count = 0;
-
-    for entry in WalkDir::new(data_dir)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        let filename = path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("");
-        
-        if filename.starts_with("questions") && filename.ends_with(".json") {
-            file_count += 1;
-            print!("Validating {}... ", filename.cyan());
-            
-            let content = fs::read_to_string(path)?;
-            let instance: Value = match serde_json::from_str(&content) {
-                Ok(v) => v,
-                Err(e) => {
-                    println!("{}", "❌ JSON PARSE ERROR".red());
-                    println!("  {} {}", "→".red(), e);
-                    results.push((filename.to_string(), false));
-                    continue;
-                }
-            };
+/// Re-validates whatever changes until interrupted: just the changed
+/// question files, or everything when the schema or food web changes. After
+/// each change the changed files and the summary of all files are printed.
+fn watch(config: &Config, validator: Validator, mut validation: Validation) -> ! {
+    let data_dir = config.paths.data_dir.as_path();
+    // Files every question file is checked against
+    let mut shared = vec![validator.schema_path.clone()];
+    if config.lint.check_foodweb {
+        shared.push(config.paths.foodweb.clone());
+    }
+    let mut watcher = Watcher::new([vec![data_dir.to_path_buf()], shared.clone()].concat());
+    // None after the schema failed to load, until it loads again
+    let mut validator = Some(validator);
+    println!("\n👀 Watching {} for changes (Ctrl+C to stop)", data_dir.display());
 
-            // Extract metadata for display
-            let metadata = instance.get("metadata");
-            let title = metadata
-                .and_then(|m| m.get("title"))
-                .and_then(|t| t.as_str())
-                .unwrap_or("No title");
-            
-            let question_count = instance
-                .get("questions")
-                .and_then(|q| q.as_array())
-                .map(|a| a.len())
-                .unwrap_or(0);
-
-            match compiled.validate(&instance) {
-                Ok(_) => {
-                    println!("{}", "✅".green());
-                    println!("  {} Title: {}", "→".dimmed(), title);
-                    println!("  {} Questions: {}", "→".dimmed(), question_count);
-                    results.push((filename.to_string(), true));
-                }
-                Err(errors) => {
-                    println!("{}", "❌".red());
-                    for error in errors {
-                        println!("  {} {}", "→".red(), error);
-                    }
-                    results.push((filename.to_string(), false));
+    loop {
+        let changed = watcher.wait();
+        let questions: Vec<&PathBuf> = changed
+            .iter()
+            .filter(|p| p.parent() == Some(data_dir) && is_question_file(p))
+            .collect();
+        let everything = validator.is_none() || changed.iter().any(|p| shared.contains(p));
+        if questions.is_empty() && !everything {
+            continue;
+        }
+
+        let names: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+        println!("\n{}", "=".repeat(50).dimmed());
+        println!(
+            "🔄 [{}] Changed: {}\n",
+            Local::now().format("%H:%M:%S"),
+            names.join(", ").cyan()
+        );
+
+        let result = if everything {
+            Validator::new(config).and_then(|v| {
+                let all = v.run()?;
+                print_text(&v.schema_path, &all);
+                validator = Some(v);
+                Ok(all)
+            })
+        } else {
+            let v = validator.as_ref().expect("validator is loaded unless everything is revalidated");
+            revalidate(v, validation.files.clone(), &questions).inspect(|update| {
+                print_files(update.files.iter().filter(|f| questions.contains(&&f.path)));
+                print_semantic(update);
+                print_summary(update);
+            })
+        };
+        match result {
+            Ok(update) => validation = update,
+            Err(e) => {
+                if everything {
+                    validator = None;
                 }
+                println!("{} {}", "Error:".red().bold(), e);
             }
-            println!();
+        }
+        println!("\n👀 Watching {} for changes (Ctrl+C to stop)", data_dir.display());
+    }
+}
+
+/// `files` with the `changed` ones checked again (or dropped, if they were
+/// deleted), and the semantic checks rerun across all of them.
+fn revalidate(validator: &Validator, mut files: Vec<FileResult>, changed: &[&PathBuf]) -> Result<Validation> {
+    for path in changed {
+        files.retain(|f| &f.path != *path);
+        if path.exists() {
+            files.push(validator.check_file(path)?);
+        } else {
+            println!("🗑️  {} removed\n", path.display().to_string().dimmed());
         }
     }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let semantic = validator.check_sets(&files)?;
+    Ok(Validation::new(files, semantic))
+}
 
-    // Summary
+fn print_text(schema_path: &Path, validation: &Validation) {
+    println!("{}", "🔍 Just Learn Just Game - Schema Validator".blue().bold());
+    println!("{}", "=".repeat(50).dimmed());
+    println!("✅ Schema loaded: {}\n", schema_path.display());
+
+    print_files(validation.files.iter());
+    print_semantic(validation);
+    print_summary(validation);
+}
+
+/// Prints the per-file passes' results.
+fn print_files<'a>(files: impl Iterator<Item = &'a FileResult>) {
+    for file in files {
+        print!("Validating {}... ", file.filename().cyan());
+        match &file.summary {
+            None => println!("{}", "❌ JSON PARSE ERROR".red()),
+            Some((title, question_count)) if file.diagnostics.is_empty() => {
+                println!("{}", "✅".green());
+                println!("  {} Title: {}", "→".dimmed(), title);
+                println!("  {} Questions: {}", "→".dimmed(), question_count);
+            }
+            Some(_) => println!("{}", "❌".red()),
+        }
+        for diagnostic in &file.diagnostics {
+            print_diagnostic(diagnostic, &file.source);
+        }
+        println!();
+    }
+}
+
+/// Prints the findings of the checks across files.
+fn print_semantic(validation: &Validation) {
+    if validation.files.iter().any(|f| f.set.is_some()) {
+        println!("{}", "Running semantic checks...".yellow());
+        if validation.semantic.is_empty() {
+            println!("  {} No issues found", "✅".green());
+        }
+        for diagnostic in &validation.semantic {
+            print_diagnostic(diagnostic, validation.source(&diagnostic.file));
+        }
+        println!();
+    }
+}
+
+fn print_summary(validation: &Validation) {
+    let (files, reports) = (&validation.files, &validation.reports);
     println!("{}", "=".repeat(50).dimmed());
     println!("{}", "📊 Validation Summary".yellow().bold());
     println!("{}", "=".repeat(50).dimmed());
-    
-    let valid_count = results.iter().filter(|(_, valid)| *valid).count();
-    let invalid_count = file_count - valid_count;
-    
-    for (filename, valid) in &results {
-        let symbol = if *valid { "✅" } else { "❌" };
-        let name = if *valid { 
-            filename.green() 
-        } else { 
-            filename.red() 
+
+    let file_count = reports.len();
+    let invalid_count = reports.iter().filter(|r| !r.is_valid()).count();
+    let warning_count = reports
+        .iter()
+        .flat_map(|r| r.diagnostics.iter())
+        .filter(|d| !d.is_error())
+        .count();
+
+    for (file, report) in files.iter().zip(reports) {
+        let valid = report.is_valid();
+        let symbol = if valid { "✅" } else { "❌" };
+        let name = if valid {
+            file.filename().green()
+        } else {
+            file.filename().red()
         };
         println!("{} {}", symbol, name);
     }
-    
+
     println!();
+    if warning_count > 0 {
+        println!("{} {} warning(s) reported", "⚠️".yellow(), warning_count);
+    }
     if invalid_count == 0 {
-        println!("🎉 {} All {} files are valid!", 
-                 "Success!".green().bold(), 
+        println!("🎉 {} All {} files are valid!",
+                 "Success!".green().bold(),
                  file_count);
-        Ok(())
     } else {
-        println!("{} {} of {} files failed validation", 
+        println!("{} {} of {} files failed validation",
                  "⚠️ Warning:".yellow().bold(),
                  invalid_count,
                  file_count);
-        process::exit(1);
     }
-}
\ No newline at end of file
+}
+
+/// Prints one finding, rustc-style, with a code frame when its position is known.
+fn print_diagnostic(diagnostic: &Diagnostic, source: &str) {
+    let (symbol, header) = match diagnostic.severity {
+        Severity::Error => ("❌", format!("error[{}]", diagnostic.rule).red().bold()),
+        Severity::Warning => ("⚠️", format!("warning[{}]", diagnostic.rule).yellow().bold()),
+    };
+    println!("  {} {}: {}", symbol, header, diagnostic.message);
+
+    let file = diagnostic.file.display().to_string();
+    match &diagnostic.location {
+        Some(location) => {
+            let label = match &diagnostic.question_id {
+                Some(id) => format!("in question '{}'", id),
+                None => String::new(),
+            };
+            for line in code_frame(source, &file, location, &label).lines() {
+                println!("    {}", line.dimmed());
+            }
+        }
+        None => {
+            let pointer = if diagnostic.pointer.is_empty() { "/" } else { &diagnostic.pointer };
+            println!("    {} {} at {}", "-->".dimmed(), file, pointer);
+        }
+    }
+}
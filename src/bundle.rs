@@ -0,0 +1,864 @@
+//! Bundling the game into a single HTML page, as `bundle` does.
+//!
+//! [`run`] loads the question sets, CSS and JS named by the configuration,
+//! renders the page template with everything embedded, and writes the page
+//! (or, with `split`, a page per set and an index), printing progress as it
+//! goes. `serve` calls [`build`] to inject its live-reload script as well.
+
+use crate::config::{BundleConfig, Config};
+use crate::csp;
+use crate::images::{data_uri, mime_type, ImageCache, OptimizeOptions};
+use crate::minify::{minify_css, minify_js};
+use crate::pwa;
+use crate::templates::{script_json, theme_stylesheet, PageTemplate};
+use crate::QuestionSet;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use colored::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the page finds one image: a data URI or a path relative to the page.
+#[derive(Serialize)]
+struct ImageAsset {
+    /// PNG, or the original file when images are not optimised.
+    src: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webp: Option<String>,
+}
+
+/// A question set to bundle. The page embeds the file's own JSON, so fields
+/// the typed model does not know about reach the game as written; the typed
+/// set is only read for the checks and the images.
+#[derive(Clone)]
+struct BundledSet {
+    set: QuestionSet,
+    json: Value,
+}
+
+/// What every page of a run shares.
+struct Shared<'a> {
+    css_content: String,
+    js_content: String,
+    build_info: BuildInfo,
+    /// Script added at the end of every page's `<body>`.
+    script: Option<&'a str>,
+}
+
+/// Bundles the game as `config` says.
+pub fn run(config: &Config) -> Result<()> {
+    build(config, None)
+}
+
+/// [`run`], with `script` added to the end of every page, before the
+/// Content-Security-Policy is computed so the policy allows it.
+pub fn build(config: &Config, script: Option<&str>) -> Result<()> {
+    let (paths, options) = (&config.paths, &config.bundle);
+    check_options(options)?;
+    println!("{}", "📦 Just Learn Just Game - Build Tool".blue().bold());
+    println!("{}", "=".repeat(50).dimmed());
+
+    // Step 1: Load all question sets
+    println!("{}", "Loading question sets...".yellow());
+    let question_sets = load_question_sets(&paths.data_dir, options)?;
+    println!("  {} Loaded {} question sets", "→".green(), question_sets.len());
+    let default_set = if options.split {
+        None
+    } else {
+        let key = default_set_key(options, &question_sets)?;
+        println!("  {} Default set: {}", "→".green(), key.cyan());
+        Some(key)
+    };
+
+    // Step 2: Load CSS and JS
+    println!("\n{}", "Loading assets...".yellow());
+    let mut sizes = Vec::new();
+    let minify = |f: fn(&str) -> Result<String>| options.release.then_some(f);
+    let mut stylesheets = paths.css.clone();
+    stylesheets.extend(theme_stylesheet(config));
+    let css_content = read_all(&stylesheets, minify(minify_css), &mut sizes)?;
+    ensure_embeddable(&css_content, "style", &stylesheets)?;
+    println!("  {} Loaded CSS ({}kb)", "→".green(), css_content.len() / 1024);
+
+    let js_content = read_all(&paths.js, minify(minify_js), &mut sizes)?;
+    ensure_embeddable(&js_content, "script", &paths.js)?;
+    println!("  {} Loaded JS ({}kb)", "→".green(), js_content.len() / 1024);
+
+    // Step 3: Generate build metadata
+    println!("\n{}", "Generating build metadata...".yellow());
+    let build_info = generate_build_info(options.reproducible)?;
+    println!("  {} Version: {}", "→".green(), build_info.version);
+    println!("  {} Timestamp: {} ({})", "→".green(), build_info.timestamp, build_info.timestamp_source);
+    let shared = Shared {
+        css_content,
+        js_content,
+        build_info,
+        script,
+    };
+
+    // Step 4: Build the page, or a page per set and an index of them
+    let output_path = paths.output.as_path();
+    match default_set {
+        Some(default_set) => build_page(config, &question_sets, &default_set, &shared, &mut sizes)?,
+        None => {
+            let page_dir = output_path.parent().unwrap_or(Path::new(""));
+            let mut pages = Vec::new();
+            for (key, bundled) in &question_sets {
+                // Self-contained: only this set, with its images inlined
+                let mut page_config = config.clone();
+                page_config.paths.output = page_dir.join(format!("{}.html", key));
+                page_config.bundle.inline_images = true;
+                println!("\n{} {}", "Set".yellow(), key.cyan());
+                let sets = BTreeMap::from([(key.clone(), bundled.clone())]);
+                build_page(&page_config, &sets, key, &shared, &mut sizes)?;
+                pages.push(page_config.paths.output);
+            }
+            println!("\n{}", "Index page".yellow());
+            write_index(config, &question_sets, &pages, &shared)?;
+        }
+    }
+    if options.release {
+        print_size_report(&sizes);
+    }
+
+    // Success!
+    println!("\n{}", "=".repeat(50).dimmed());
+    println!("✨ {} Build complete!", "Success!".green().bold());
+    if options.split {
+        println!("🎯 Open {} in any browser to choose a set", output_path.display());
+    } else {
+        println!("🎯 Open {} in any browser to play", output_path.display());
+    }
+
+    Ok(())
+}
+
+/// Bundles `question_sets` into the page at `paths.output`.
+fn build_page(
+    config: &Config,
+    question_sets: &BTreeMap<String, BundledSet>,
+    default_set: &str,
+    shared: &Shared,
+    sizes: &mut Vec<AssetSize>,
+) -> Result<()> {
+    let (paths, options) = (&config.paths, &config.bundle);
+    let output_path = paths.output.as_path();
+    let page_dir = output_path.parent().unwrap_or(Path::new(""));
+    // Names the JSON sizes in the release report when there are several pages
+    let prefix = if options.split { format!("{} ", default_set) } else { String::new() };
+
+    let image_assets = if options.inline_images || options.optimize_images {
+        println!("\n{}", "Processing images...".yellow());
+        Some(bundle_images(options, question_sets, &paths.asset_root, page_dir)?)
+    } else {
+        None
+    };
+    // Image files the page loads, relative to it
+    let image_files: Vec<String> = match &image_assets {
+        Some(assets) => assets
+            .values()
+            .flat_map(|asset| [Some(&asset.src), asset.webp.as_ref()])
+            .flatten()
+            .filter(|url| !url.starts_with("data:"))
+            .cloned()
+            .collect(),
+        None => referenced_images(question_sets).into_keys().map(str::to_string).collect(),
+    };
+    // Hashed with the page, so that changing only an image still changes
+    // the content hash and with it the offline cache
+    let image_root = if image_assets.is_some() { page_dir } else { paths.asset_root.as_path() };
+    let image_contents = image_files
+        .iter()
+        .map(|url| {
+            let path = image_root.join(url);
+            let bytes = fs::read(&path).with_context(|| format!("Failed to read image {}", path.display()))?;
+            Ok((url.clone(), bytes))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    // Load the template and prepare its data. Sets and images are in key
+    // order, so the same inputs always serialise to the same JSON
+    println!("\n{}", "Loading template...".yellow());
+    let image_urls = image_assets
+        .iter()
+        .flatten()
+        .map(|(name, asset)| (name.clone(), asset.src.clone()))
+        .collect();
+    let template = PageTemplate::load(config, &paths.template, image_urls)?;
+    print_template(config, &template);
+    let sources: BTreeMap<&str, &Value> = question_sets.iter().map(|(key, set)| (key.as_str(), &set.json)).collect();
+    let question_sets_json =
+        to_json(&sources, options.release, &format!("{}question sets JSON", prefix), sizes)?;
+    let default_set_json = to_json(
+        &question_sets[default_set].json,
+        options.release,
+        &format!("{}default set JSON", prefix),
+        sizes,
+    )?;
+    let mut template_data = json!({
+        "question_sets_json": question_sets_json,
+        "default_question_set": default_set_json,
+        "default_question_set_key": script_json(&serde_json::to_string(default_set)?),
+        "image_assets_json": image_assets
+            .map(|assets| serde_json::to_string(&assets).map(|json| script_json(&json)))
+            .transpose()?,
+        "css_content": shared.css_content,
+        "js_content": shared.js_content,
+        "lang": options.lang,
+        "strings": template.catalogue(),
+        "pwa": options.pwa.then(|| json!({
+            "manifest": pwa::MANIFEST,
+            "service_worker": pwa::SERVICE_WORKER,
+            "icon": pwa::icon_path(pwa::ICON_SIZES[0]),
+            "theme_color": config.pwa.theme_color,
+        })),
+    });
+    let content_hash = stamp(&mut template_data, &shared.build_info, template.sources(), &image_contents);
+
+    println!("\n{}", "Rendering template...".yellow());
+    let output = template.render(&template_data)?;
+    write_page(config, output, shared)?;
+
+    if options.pwa {
+        println!("\n{}", "Writing offline app files...".yellow());
+        write_pwa(config, &template, output_path, &content_hash, image_files)?;
+    }
+    Ok(())
+}
+
+/// Writes the page of `--split` that links the per-set `pages`.
+fn write_index(
+    config: &Config,
+    question_sets: &BTreeMap<String, BundledSet>,
+    pages: &[PathBuf],
+    shared: &Shared,
+) -> Result<()> {
+    let paths = &config.paths;
+    let template = PageTemplate::load(config, &paths.index_template, BTreeMap::new())?;
+    print_template(config, &template);
+    let sets: Vec<Value> = question_sets
+        .iter()
+        .zip(pages)
+        .map(|((key, BundledSet { set, .. }), page)| {
+            json!({
+                "key": key,
+                "href": page.file_name().map(|name| name.to_string_lossy()),
+                "title": set.metadata.title,
+                "description": set.metadata.description,
+                "target_age": set.metadata.target_age,
+                "mode": set.metadata.mode,
+                "question_count": set.questions.len(),
+            })
+        })
+        .collect();
+    let mut template_data = json!({
+        "sets": sets,
+        "css_content": shared.css_content,
+        "lang": config.bundle.lang,
+    });
+    stamp(&mut template_data, &shared.build_info, template.sources(), &BTreeMap::new());
+    let output = template.render(&template_data)?;
+    write_page(config, output, shared)
+}
+
+fn print_template(config: &Config, template: &PageTemplate) {
+    println!("  {} Template: {}", "→".green(), template.template.display());
+    if let Some(theme) = &config.paths.theme {
+        println!("  {} Theme: {}", "→".green(), theme.display());
+    }
+    println!(
+        "  {} {} partial(s), strings: {}",
+        "→".green(),
+        template.partials.len(),
+        template
+            .strings
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    );
+}
+
+/// Adds the build metadata to `template_data`, with the content hash of
+/// the page it describes and the image files it loads, which is returned.
+fn stamp(
+    template_data: &mut Value,
+    build_info: &BuildInfo,
+    templates: &BTreeMap<String, String>,
+    images: &BTreeMap<String, Vec<u8>>,
+) -> String {
+    let content_hash = content_hash(templates, template_data, images);
+    println!("  {} Content hash: {}", "→".green(), content_hash);
+    template_data["build_timestamp"] = json!(build_info.timestamp);
+    template_data["build_timestamp_unix"] = json!(build_info.timestamp_unix);
+    template_data["version"] = json!(build_info.version);
+    template_data["content_hash"] = json!(content_hash);
+    content_hash
+}
+
+/// Adds the shared script and the Content-Security-Policy to a rendered
+/// page, if enabled, and writes it to `paths.output`.
+fn write_page(config: &Config, mut output: String, shared: &Shared) -> Result<()> {
+    let options = &config.bundle;
+    let output_path = config.paths.output.as_path();
+    let page_dir = output_path.parent().unwrap_or(Path::new(""));
+    if let Some(script) = shared.script {
+        let end = output.rfind("</body>").unwrap_or(output.len());
+        output.insert_str(end, &format!("<script>\n{}\n</script>\n", script));
+    }
+    if options.csp {
+        let mut policy = csp::Policy::default();
+        if options.pwa {
+            policy.workers.insert("'self'".to_string());
+        }
+        let (page, policy) = csp::secure_page(&output, page_dir, policy)?;
+        println!(
+            "  {} Content-Security-Policy: {} script(s), {} style(s), {} style attribute(s) allowed by hash",
+            "→".green(),
+            policy.scripts.len(),
+            policy.styles.len(),
+            policy.style_attributes.len()
+        );
+        output = page;
+    }
+
+    if !page_dir.as_os_str().is_empty() {
+        fs::create_dir_all(page_dir)
+            .with_context(|| format!("Failed to create {}", page_dir.display()))?;
+    }
+    fs::write(output_path, output)
+        .with_context(|| format!("Failed to write {}", output_path.display()))?;
+
+    let output_size = fs::metadata(output_path)?.len() / 1024;
+    println!("  {} Written {} ({}kb)", "→".green(), output_path.display(), output_size);
+    Ok(())
+}
+
+/// Writes the manifest, icons and service worker of `bundle --pwa` next to
+/// the page. The worker precaches the page, those files and `image_files`.
+fn write_pwa(
+    config: &Config,
+    template: &PageTemplate,
+    page: &Path,
+    content_hash: &str,
+    image_files: Vec<String>,
+) -> Result<()> {
+    let page_dir = page.parent().unwrap_or(Path::new(""));
+    let start_url = page
+        .file_name()
+        .context("The output path has no file name")?
+        .to_string_lossy()
+        .to_string();
+
+    let icon_source = config.paths.asset_root.join(&config.pwa.icon);
+    let icon = fs::read(&icon_source)
+        .with_context(|| format!("Failed to read the app icon {}", icon_source.display()))?;
+    let icons = pwa::write_icons(&icon, page_dir)?;
+    println!("  {} Icons: {} (from {})", "→".green(), icons.join(", "), icon_source.display());
+
+    let app = pwa::App {
+        name: template.text("title")?.to_string(),
+        short_name: template.text("short-title")?.to_string(),
+        description: template.text("start.description")?.to_string(),
+        lang: config.bundle.lang.clone(),
+        start_url: start_url.clone(),
+        theme_color: config.pwa.theme_color.clone(),
+        background_color: config.pwa.background_color.clone(),
+    };
+    let manifest_path = page_dir.join(pwa::MANIFEST);
+    fs::write(&manifest_path, serde_json::to_string_pretty(&pwa::manifest(&app))?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+    println!("  {} Manifest: {}", "→".green(), manifest_path.display());
+
+    let mut precache = vec![start_url.clone(), pwa::MANIFEST.to_string()];
+    precache.extend(icons);
+    precache.extend(image_files);
+    let script_path = &config.paths.service_worker;
+    let mut script = fs::read_to_string(script_path)
+        .with_context(|| format!("Failed to read {}", script_path.display()))?;
+    if config.bundle.release {
+        script = minify_js(&script).with_context(|| format!("Failed to minify {}", script_path.display()))?;
+    }
+    let worker_path = page_dir.join(pwa::SERVICE_WORKER);
+    fs::write(&worker_path, pwa::service_worker(&script, content_hash, &start_url, &precache))
+        .with_context(|| format!("Failed to write {}", worker_path.display()))?;
+    println!(
+        "  {} Service worker: {} ({} files precached)",
+        "→".green(),
+        worker_path.display(),
+        precache.len()
+    );
+    Ok(())
+}
+
+/// Rejects option combinations that can't work.
+fn check_options(options: &BundleConfig) -> Result<()> {
+    if options.webp && !options.optimize_images {
+        anyhow::bail!("WebP versions are only made of optimised images; add --optimize-images");
+    }
+    if options.split && options.pwa {
+        anyhow::bail!("--split pages are self-contained files to hand out; they can't be combined with --pwa");
+    }
+    if options.split && options.default_set.is_some() {
+        anyhow::bail!("Each --split page opens its own set; leave out --default-set");
+    }
+    Ok(())
+}
+
+fn load_question_sets(data_dir: &Path, options: &BundleConfig) -> Result<BTreeMap<String, BundledSet>> {
+    let mut question_sets = BTreeMap::new();
+    let files = crate::load_question_sets(data_dir)?;
+
+    for pattern in options.include.iter().chain(&options.exclude) {
+        if !files.iter().any(|f| matches_key(pattern, &f.key)) {
+            let available: Vec<&str> = files.iter().map(|f| f.key.as_str()).collect();
+            anyhow::bail!(
+                "'{}' matches no question set in {} (available: {})",
+                pattern,
+                data_dir.display(),
+                available.join(", ")
+            );
+        }
+    }
+
+    for file in files {
+        let included = options.include.is_empty() || options.include.iter().any(|p| matches_key(p, &file.key));
+        let excluded = options.exclude.iter().any(|p| matches_key(p, &file.key));
+        if !included || excluded {
+            println!("    {} {}: {}", "•".dimmed(), file.key.dimmed(), "skipped".dimmed());
+            continue;
+        }
+        println!("    {} {}: {}", "•".dimmed(), file.key.cyan(), file.set.metadata.title.dimmed());
+        let source = fs::read_to_string(&file.path)
+            .with_context(|| format!("Failed to read {}", file.path.display()))?;
+        let json = serde_json::from_str(&source)
+            .with_context(|| format!("Failed to parse {}", file.path.display()))?;
+        question_sets.insert(file.key, BundledSet { set: file.set, json });
+    }
+    
+    if question_sets.is_empty() {
+        anyhow::bail!("No question sets left to bundle from {}", data_dir.display());
+    }
+    
+    Ok(question_sets)
+}
+
+/// Matches a set key against a pattern where `*` stands for any run of characters.
+fn matches_key(pattern: &str, key: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == key,
+        Some((prefix, rest)) => {
+            let Some(key) = key.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=key.len())
+                .filter(|&i| key.is_char_boundary(i))
+                .any(|i| matches_key(rest, &key[i..]))
+        }
+    }
+}
+
+/// The `--default-set`, or `questions`, or the first set by key.
+fn default_set_key(options: &BundleConfig, question_sets: &BTreeMap<String, BundledSet>) -> Result<String> {
+    if let Some(key) = &options.default_set {
+        if !question_sets.contains_key(key) {
+            anyhow::bail!("Default set {} is not one of the bundled question sets", key);
+        }
+        return Ok(key.clone());
+    }
+    if question_sets.contains_key("questions") {
+        return Ok("questions".to_string());
+    }
+    let first = question_sets.keys().next().context("No question sets to choose from")?;
+    Ok(first.clone())
+}
+
+/// Fails if `content` would end its `<style>`/`<script>` block early.
+fn ensure_embeddable(content: &str, element: &str, paths: &[PathBuf]) -> Result<()> {
+    let end_tag = format!("</{}", element);
+    if content.to_ascii_lowercase().contains(&end_tag) {
+        let files: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        anyhow::bail!(
+            "{} contains '{}', which would end the embedded <{}> block; split it up, e.g. '<\\/{}'",
+            files.join(", "),
+            end_tag,
+            element,
+            element
+        );
+    }
+    Ok(())
+}
+
+/// Size of one embedded asset before and after a release build shrank it.
+struct AssetSize {
+    name: String,
+    before: usize,
+    after: usize,
+}
+
+/// Reads and concatenates `paths`, separated by blank lines. With a
+/// `minify` function each file is minified first and its sizes recorded.
+fn read_all(
+    paths: &[PathBuf],
+    minify: Option<fn(&str) -> Result<String>>,
+    sizes: &mut Vec<AssetSize>,
+) -> Result<String> {
+    let contents = paths
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let Some(minify) = minify else {
+                return Ok(content);
+            };
+            let minified =
+                minify(&content).with_context(|| format!("Failed to minify {}", path.display()))?;
+            sizes.push(AssetSize {
+                name: path.display().to_string(),
+                before: content.len(),
+                after: minified.len(),
+            });
+            Ok(minified)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(contents.join("\n\n"))
+}
+
+/// Serialises embedded data: pretty-printed for readable development
+/// builds, compact (with its saving recorded) for release builds. Either
+/// way the result is safe to embed in a `<script>` block.
+fn to_json<T: Serialize>(value: &T, release: bool, name: &str, sizes: &mut Vec<AssetSize>) -> Result<String> {
+    let pretty = script_json(&serde_json::to_string_pretty(value)?);
+    if !release {
+        return Ok(pretty);
+    }
+    let compact = script_json(&serde_json::to_string(value)?);
+    sizes.push(AssetSize {
+        name: name.to_string(),
+        before: pretty.len(),
+        after: compact.len(),
+    });
+    Ok(compact)
+}
+
+fn print_size_report(sizes: &[AssetSize]) {
+    println!("\n{}", "Release size report:".yellow());
+    let width = sizes.iter().map(|s| s.name.len()).max().unwrap_or(0).max("total".len());
+    let row = |name: &str, before: usize, after: usize| {
+        let saved = if before == 0 { 0.0 } else { 100.0 * (before - after) as f64 / before as f64 };
+        format!(
+            "{:<width$}  {:>7.1}kb → {:>7.1}kb  (-{:.0}%)",
+            name,
+            before as f64 / 1024.0,
+            after as f64 / 1024.0,
+            saved,
+            width = width
+        )
+    };
+    for size in sizes {
+        println!("  {} {}", "•".dimmed(), row(&size.name, size.before, size.after));
+    }
+    let before = sizes.iter().map(|s| s.before).sum();
+    let after = sizes.iter().map(|s| s.after).sum();
+    println!("  {} {}", "→".green(), row("total", before, after).bold());
+}
+
+/// Every image the questions reference, with the first question using it
+/// (for error messages). Remote and `data:` URLs are left alone.
+fn referenced_images(question_sets: &BTreeMap<String, BundledSet>) -> BTreeMap<&str, &str> {
+    let mut referenced = BTreeMap::new();
+    for BundledSet { set, .. } in question_sets.values() {
+        for question in &set.questions {
+            for image in question.images() {
+                if !image.starts_with("data:") && !image.contains("://") {
+                    referenced.entry(image).or_insert(question.id.as_str());
+                }
+            }
+        }
+    }
+    referenced
+}
+
+/// Reads every referenced image from `asset_root`, optionally optimises it,
+/// and either inlines it as a data URI or writes it to `--image-out-dir`. Each image is processed once however
+/// many questions use it. Prints the size cost of every image.
+fn bundle_images(
+    options: &BundleConfig,
+    question_sets: &BTreeMap<String, BundledSet>,
+    asset_root: &Path,
+    page_dir: &Path,
+) -> Result<BTreeMap<String, ImageAsset>> {
+    let cache = ImageCache::new(&options.image_cache);
+    let optimize = OptimizeOptions {
+        max_dimension: options.max_image_dimension,
+        png_colors: (options.image_colors > 0).then_some(options.image_colors),
+        webp_quality: options.webp.then_some(options.webp_quality),
+    };
+
+    let mut assets = BTreeMap::new();
+    let (mut source_total, mut output_total) = (0, 0);
+    for (name, question_id) in referenced_images(question_sets) {
+        let path = asset_root.join(name);
+        let bytes = fs::read(&path).with_context(|| {
+            format!(
+                "Failed to read image {} (used by question '{}')",
+                path.display(),
+                question_id
+            )
+        })?;
+        let mut report = format!("{}: {}kb", name.cyan(), bytes.len() / 1024);
+
+        // (data, mime type) of the main image, and the WebP version if any
+        let (src, webp) = if options.optimize_images {
+            let image = cache
+                .optimize(&bytes, &optimize)
+                .with_context(|| format!("Failed to optimise {}", path.display()))?;
+            report += &format!(
+                " {}×{} → {}×{} png {}kb",
+                image.source_width,
+                image.source_height,
+                image.width,
+                image.height,
+                image.png.len() / 1024
+            );
+            if let Some(webp) = &image.webp {
+                report += &format!(", webp {}kb", webp.len() / 1024);
+            }
+            if image.cached {
+                report += &format!(" {}", "(cached)".dimmed());
+            }
+            ((image.png, "image/png"), image.webp)
+        } else {
+            let mime = mime_type(name)?;
+            ((bytes.clone(), mime), None)
+        };
+
+        let asset = if options.inline_images {
+            let asset = ImageAsset {
+                src: data_uri(&src.0, src.1),
+                webp: webp.as_deref().map(|w| data_uri(w, "image/webp")),
+            };
+            let size = asset.src.len() + asset.webp.as_ref().map_or(0, String::len);
+            report += &format!(" → {}kb in page", size / 1024);
+            output_total += size;
+            asset
+        } else {
+            let png_name = Path::new(name).with_extension("png");
+            let webp_name = Path::new(name).with_extension("webp");
+            let write = |file: &Path, data: &[u8]| -> Result<String> {
+                let out = page_dir.join(&options.image_out_dir).join(file);
+                if let Some(dir) = out.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&out, data).with_context(|| format!("Failed to write {}", out.display()))?;
+                Ok(options.image_out_dir.join(file).to_string_lossy().replace('\\', "/"))
+            };
+            output_total += src.0.len() + webp.as_ref().map_or(0, Vec::len);
+            ImageAsset {
+                src: write(&png_name, &src.0)?,
+                webp: webp.as_deref().map(|w| write(&webp_name, w)).transpose()?,
+            }
+        };
+
+        println!("    {} {}", "•".dimmed(), report);
+        source_total += bytes.len();
+        assets.insert(name.to_string(), asset);
+    }
+
+    let destination = if options.inline_images {
+        "embedded in the page".to_string()
+    } else {
+        format!("written to {}", page_dir.join(&options.image_out_dir).display())
+    };
+    println!(
+        "  {} {} images: {}kb of source files → {}kb {}",
+        "→".green(),
+        assets.len(),
+        source_total / 1024,
+        output_total / 1024,
+        destination
+    );
+    Ok(assets)
+}
+
+struct BuildInfo {
+    version: String,
+    timestamp: String,
+    timestamp_unix: i64,
+    /// Where the timestamp came from, for the build log.
+    timestamp_source: &'static str,
+}
+
+/// Version and timestamp of this build.
+///
+/// The timestamp follows the reproducible-builds convention: an explicit
+/// `SOURCE_DATE_EPOCH` always wins, and a `reproducible` build without one
+/// uses the commit time of HEAD. Either way it is formatted in UTC, so the
+/// builder's time zone does not leak into the page.
+fn generate_build_info(reproducible: bool) -> Result<BuildInfo> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|s| s.trim().to_string())
+    };
+
+    // A fixed abbreviation length: the default grows with the object count,
+    // so shallow and full clones would disagree
+    let version = git(&["rev-parse", "--short=12", "HEAD"])
+        .map(|hash| format!("git-{}", hash))
+        .unwrap_or_else(|| "dev-build".to_string());
+
+    let (timestamp, timestamp_source) = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => (
+            parse_epoch(&epoch)
+                .map_err(|e| anyhow::anyhow!("Invalid SOURCE_DATE_EPOCH: {}", e))?
+                .fixed_offset(),
+            "SOURCE_DATE_EPOCH",
+        ),
+        Err(_) if reproducible => {
+            let epoch = git(&["log", "-1", "--format=%ct"])
+                .context("--reproducible needs SOURCE_DATE_EPOCH or a git checkout to date the build")?;
+            (parse_epoch(&epoch)?.fixed_offset(), "HEAD commit time")
+        }
+        Err(_) => (Local::now().fixed_offset(), "current time"),
+    };
+
+    Ok(BuildInfo {
+        version,
+        timestamp: timestamp.to_rfc3339(),
+        timestamp_unix: timestamp.timestamp(),
+        timestamp_source,
+    })
+}
+
+fn parse_epoch(epoch: &str) -> Result<DateTime<Utc>> {
+    let seconds: i64 = epoch
+        .trim()
+        .parse()
+        .with_context(|| format!("'{}' is not a number of seconds", epoch.trim()))?;
+    DateTime::from_timestamp(seconds, 0).with_context(|| format!("{} is out of range", seconds))
+}
+
+/// SHA-256 over the templates, everything embedded in the page and the
+/// image files it loads, but not the build metadata: two builds with the
+/// same content have the same hash whenever and from whichever commit they
+/// were made, so a deployed page can be checked against a rebuild.
+fn content_hash(templates: &BTreeMap<String, String>, content: &Value, images: &BTreeMap<String, Vec<u8>>) -> String {
+    let mut hasher = Sha256::new();
+    let mut field = |name: &str, value: &[u8]| {
+        // Length-prefixed so adjacent fields can't run into each other
+        hasher.update(name.as_bytes());
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value);
+    };
+    for (name, source) in templates {
+        field(name, source.as_bytes());
+    }
+    if let Value::Object(fields) = content {
+        for (name, value) in fields {
+            match value {
+                Value::String(text) => field(name, text.as_bytes()),
+                other => field(name, other.to_string().as_bytes()),
+            }
+        }
+    }
+    for (url, bytes) in images {
+        field(url, bytes);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory for one test, emptied first.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bundle-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The repository's configuration with paths made absolute, writing the
+    /// page to `dir`.
+    fn config(dir: &Path) -> Config {
+        let mut config = Config::from_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("game.toml")).unwrap();
+        config.paths.output = dir.join("index.html");
+        config
+    }
+
+    #[test]
+    fn embeds_the_string_catalogue_of_the_page_language() {
+        let dir = scratch("lang");
+        let strings = dir.join("strings");
+        fs::create_dir_all(&strings).unwrap();
+        let mut german: BTreeMap<String, String> =
+            serde_json::from_str(&fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/strings/en.json")).unwrap())
+                .unwrap();
+        german.insert("game.counter".to_string(), "Frage {current} von {total}".to_string());
+        german.insert("result.correct".to_string(), "Richtig!".to_string());
+        fs::write(strings.join("de.json"), serde_json::to_string(&german).unwrap()).unwrap();
+
+        let mut config = config(&dir);
+        config.paths.strings = strings;
+        config.bundle.lang = "de".to_string();
+        run(&config).unwrap();
+
+        let page = fs::read_to_string(dir.join("index.html")).unwrap();
+        let catalogue = page
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("window.STRINGS = "))
+            .expect("window.STRINGS is embedded");
+        let catalogue: BTreeMap<String, String> =
+            serde_json::from_str(catalogue.trim_end_matches(';')).unwrap();
+        assert_eq!(catalogue, german);
+        // The markup uses the same strings
+        assert!(page.contains(">Richtig!</h2>"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn embeds_question_sets_as_written() {
+        let dir = scratch("sets");
+        let data = dir.join("data");
+        fs::create_dir_all(&data).unwrap();
+        let source = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("data/questions_ross_sea_easy.json")).unwrap();
+        let mut set: Value = serde_json::from_str(&source).unwrap();
+        // Fields the typed model does not know about
+        set["metadata"]["author"] = json!("Ross Sea Team");
+        set["questions"][0]["hint"] = json!("Look at the arrows");
+        let written = serde_json::to_string_pretty(&set).unwrap();
+        fs::write(data.join("questions_custom.json"), &written).unwrap();
+
+        let mut config = config(&dir);
+        config.paths.data_dir = data;
+        run(&config).unwrap();
+
+        let page = fs::read_to_string(dir.join("index.html")).unwrap();
+        let embedded = format!("window.EMBEDDED_GAME_DATA = {};", script_json(&written));
+        assert!(page.contains(&embedded), "the default set is embedded unchanged");
+        assert!(page.contains("\"hint\": \"Look at the arrows\""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn content_hash_covers_image_files() {
+        let templates = BTreeMap::from([("index.hbs".to_string(), "<p>{{title}}</p>".to_string())]);
+        let content = json!({ "title": "Quiz" });
+        let images = |png: &[u8]| BTreeMap::from([("KR.png".to_string(), png.to_vec())]);
+        let hash = content_hash(&templates, &content, &images(b"krill"));
+        assert_eq!(hash, content_hash(&templates, &content, &images(b"krill")));
+        assert_ne!(hash, content_hash(&templates, &content, &images(b"krill!")));
+        assert_ne!(hash, content_hash(&templates, &content, &BTreeMap::new()));
+    }
+}
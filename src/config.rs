@@ -0,0 +1,506 @@
+//! The project manifest, `game.toml`.
+//!
+//! Every tool used to hardcode where things live (`data/`, `templates/`,
+//! `index.html`, a 1280×800 viewport, ...). `game.toml` declares those once
+//! for `validate`, `bundle`, `serve`, `test-runner`, `render-all-questions`
+//! and `screenshot-layout-test`. Every key is optional and falls back to the
+//! layout of this repository, so a missing file or an empty one behaves
+//! exactly like the tools did before the manifest existed.
+//!
+//! Each tool loads the manifest with [`ConfigArgs::load`], then lets its own
+//! command-line flags override the file, and `--print-config` shows the
+//! result as TOML:
+//!
+//! ```toml
+//! [paths]
+//! data-dir = "data"
+//! output = "dist/index.html"
+//!
+//! [[viewports]]
+//! name = "tablet"
+//! width = 820
+//! height = 1180
+//! scale = 2.0
+//! mobile = true
+//!
+//! [lint.rules]
+//! mode-difficulty = "allow"
+//! foodweb-distractor = "error"
+//! ```
+//!
+//! Relative paths in the file are relative to the file, so
+//! `--config site/game.toml` works from anywhere; paths given on the command
+//! line are relative to the working directory as usual.
+
+use crate::validation::{Diagnostic, Severity, RULES, RULE_JSON_SYNTAX, RULE_SCHEMA};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The manifest read when `--config` is not given, if it exists.
+pub const DEFAULT_CONFIG: &str = "game.toml";
+
+/// The effective configuration of a tool run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub paths: Paths,
+    pub bundle: BundleConfig,
+    pub test: TestConfig,
+    /// Screen sizes the browser tools emulate. `test-runner` uses the one
+    /// named by `test.viewport`; `screenshot-layout-test` captures them all.
+    pub viewports: Vec<Viewport>,
+    pub lint: LintConfig,
+    /// Credits rendered by the template's `attribution` helper.
+    pub attribution: Attribution,
+    /// The installed app, for `bundle --pwa`.
+    pub pwa: PwaConfig,
+    pub serve: ServeConfig,
+}
+
+/// Where inputs are read from and outputs written to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Paths {
+    /// Directory containing the `questions*.json` files.
+    pub data_dir: PathBuf,
+    /// JSON Schema for question sets. Defaults to `schema.json` in `data-dir`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<PathBuf>,
+    /// Markdown, `.mmd` or `.json` file holding the food web.
+    pub foodweb: PathBuf,
+    /// Directory the question images (`image1`/`image2`) are read from.
+    pub asset_root: PathBuf,
+    /// Handlebars template for the bundled page.
+    pub template: PathBuf,
+    /// Template of the index page `bundle --split` writes to `output`.
+    pub index_template: PathBuf,
+    /// Directory of Handlebars partials (`*.hbs`) the template can include.
+    pub partials: PathBuf,
+    /// Directory of translation strings (`<lang>.json`) for the `t` helper.
+    pub strings: PathBuf,
+    /// Theme directory overriding the template, partials, strings and
+    /// styles (see [`crate::templates`]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<PathBuf>,
+    /// Stylesheets embedded in the page, in order.
+    pub css: Vec<PathBuf>,
+    /// Scripts embedded in the page, in order.
+    pub js: Vec<PathBuf>,
+    /// Service worker script written next to the page by `bundle --pwa`.
+    pub service_worker: PathBuf,
+    /// The bundled page, and the page the browser tools open.
+    pub output: PathBuf,
+    /// Where `test-runner` writes its screenshots.
+    pub test_output: PathBuf,
+    /// Where `screenshot-layout-test` writes its screenshots.
+    pub screenshots: PathBuf,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            schema: None,
+            foodweb: PathBuf::from("README.md"),
+            asset_root: PathBuf::from("."),
+            template: PathBuf::from("templates/index.hbs"),
+            index_template: PathBuf::from("templates/sets.hbs"),
+            partials: PathBuf::from("templates/partials"),
+            strings: PathBuf::from("templates/strings"),
+            theme: None,
+            css: vec![PathBuf::from("css/style.css")],
+            js: vec![PathBuf::from("js/game-engine.js"), PathBuf::from("js/ui.js")],
+            service_worker: PathBuf::from("js/service-worker.js"),
+            output: PathBuf::from("index.html"),
+            test_output: PathBuf::from("test_output"),
+            screenshots: PathBuf::from("."),
+        }
+    }
+}
+
+impl Paths {
+    /// The schema path, defaulting to `schema.json` in the data directory.
+    pub fn schema(&self) -> PathBuf {
+        self.schema.clone().unwrap_or_else(|| self.data_dir.join("schema.json"))
+    }
+
+    /// Makes relative paths relative to `base` instead of the working directory.
+    fn rebase(&mut self, base: &Path) {
+        let rebase = |path: &mut PathBuf| rebase_path(path, base);
+        rebase(&mut self.data_dir);
+        if let Some(schema) = &mut self.schema {
+            rebase(schema);
+        }
+        rebase(&mut self.foodweb);
+        rebase(&mut self.asset_root);
+        rebase(&mut self.template);
+        rebase(&mut self.index_template);
+        rebase(&mut self.partials);
+        rebase(&mut self.strings);
+        if let Some(theme) = &mut self.theme {
+            rebase(theme);
+        }
+        self.css.iter_mut().for_each(rebase);
+        self.js.iter_mut().for_each(rebase);
+        rebase(&mut self.service_worker);
+        rebase(&mut self.output);
+        rebase(&mut self.test_output);
+        rebase(&mut self.screenshots);
+    }
+}
+
+/// Makes `path` relative to `base` instead of the working directory, if it
+/// is relative.
+fn rebase_path(path: &mut PathBuf, base: &Path) {
+    if path.is_relative() {
+        *path = base.join(&*path);
+    }
+}
+
+/// What `bundle` puts in the page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BundleConfig {
+    /// Set keys to bundle (`*` wildcards allowed); empty means all.
+    pub include: Vec<String>,
+    /// Set keys to leave out (`*` wildcards allowed).
+    pub exclude: Vec<String>,
+    /// Set selected when the page opens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_set: Option<String>,
+    pub inline_images: bool,
+    pub optimize_images: bool,
+    pub max_image_dimension: u32,
+    /// Palette size of optimised PNGs; 0 keeps full colour.
+    pub image_colors: u16,
+    pub webp: bool,
+    pub webp_quality: f32,
+    /// Where optimised images are cached between builds.
+    pub image_cache: PathBuf,
+    /// Directory, relative to the page, that optimised images are written to.
+    pub image_out_dir: PathBuf,
+    /// Minify embedded CSS/JS and compact embedded JSON.
+    pub release: bool,
+    /// Inject a Content-Security-Policy allowing only the page's own
+    /// inline scripts and styles, by hash.
+    pub csp: bool,
+    /// Stamp the page with the HEAD commit time rather than the current
+    /// time when `SOURCE_DATE_EPOCH` is not set.
+    pub reproducible: bool,
+    /// Language of the page's own text, i.e. which `<lang>.json` the `t`
+    /// helper reads.
+    pub lang: String,
+    /// Also write a web app manifest, icons and an offline service worker.
+    pub pwa: bool,
+    /// Write a self-contained page per set plus an index page linking them.
+    pub split: bool,
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            default_set: None,
+            inline_images: false,
+            optimize_images: false,
+            max_image_dimension: 640,
+            image_colors: 256,
+            webp: false,
+            webp_quality: 80.0,
+            image_cache: PathBuf::from(".cache/images"),
+            image_out_dir: PathBuf::from("img"),
+            release: false,
+            csp: true,
+            reproducible: false,
+            lang: "en".to_string(),
+            pwa: false,
+            split: false,
+        }
+    }
+}
+
+/// Who the content comes from, shown in the page footer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Attribution {
+    pub copyright: String,
+    /// Name and address of the site the content comes from, shown as a link.
+    pub source_name: String,
+    pub source_url: String,
+    /// Further credit, e.g. for the diagram the questions are based on.
+    pub credit: String,
+}
+
+impl Default for Attribution {
+    fn default() -> Self {
+        Self {
+            copyright: "© University of Waikato".to_string(),
+            source_name: "sciencelearn.org.nz".to_string(),
+            source_url: "https://www.sciencelearn.org.nz".to_string(),
+            credit: "Based on M. Pinkerton/NIWA diagram".to_string(),
+        }
+    }
+}
+
+/// How the game looks when installed as an app.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PwaConfig {
+    /// Image under `paths.asset-root` the app icons are made from.
+    pub icon: String,
+    /// Colour of the browser UI around the installed app.
+    pub theme_color: String,
+    /// Colour of the splash screen while the app starts.
+    pub background_color: String,
+}
+
+impl Default for PwaConfig {
+    fn default() -> Self {
+        Self {
+            icon: "PG.png".to_string(),
+            theme_color: "#0288d1".to_string(),
+            background_color: "#01579b".to_string(),
+        }
+    }
+}
+
+/// Where `serve` listens and builds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ServeConfig {
+    pub host: String,
+    pub port: u16,
+    /// Where the served pages are built, so `paths.output` is left alone.
+    pub dir: PathBuf,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8000,
+            dir: PathBuf::from(".cache/serve"),
+        }
+    }
+}
+
+/// How `test-runner` drives the browser.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TestConfig {
+    pub headless: bool,
+    /// Timeout for page operations, in seconds.
+    pub timeout: u64,
+    /// Name of the entry in `viewports` to test with.
+    pub viewport: String,
+    /// Question sets run at once, each in its own browser context.
+    pub jobs: usize,
+    /// Start no further question sets once this many have failed; 0 runs
+    /// them all.
+    pub max_failures: usize,
+    /// Page errors that don't fail a test: console errors, uncaught
+    /// exceptions and failed loads containing any of these.
+    pub allow_errors: Vec<String>,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            timeout: 10,
+            viewport: "desktop".to_string(),
+            jobs: 1,
+            max_failures: 1,
+            allow_errors: Vec::new(),
+        }
+    }
+}
+
+/// An emulated screen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Viewport {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Device pixel ratio.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Emulate a phone: mobile layout and touch events.
+    #[serde(default)]
+    pub mobile: bool,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// The viewports the tools used before they were configurable.
+pub fn default_viewports() -> Vec<Viewport> {
+    vec![
+        Viewport {
+            name: "desktop".to_string(),
+            width: 1280,
+            height: 800,
+            scale: 1.0,
+            mobile: false,
+        },
+        Viewport {
+            name: "mobile".to_string(),
+            width: 390,
+            height: 844,
+            scale: 3.0,
+            mobile: true,
+        },
+    ]
+}
+
+/// How `validate` treats each rule.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// Run the food-web fact check (`validate --check-foodweb`).
+    pub check_foodweb: bool,
+    /// Level per rule id; rules not listed keep their built-in severity.
+    pub rules: BTreeMap<String, Level>,
+}
+
+/// What to do with a rule's findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Drop the findings.
+    Allow,
+    /// Report them without failing validation.
+    Warn,
+    /// Report them and fail validation.
+    Error,
+}
+
+impl LintConfig {
+    /// Sets `rule` to `level`, rejecting unknown rule ids.
+    pub fn set(&mut self, rule: &str, level: Level) -> Result<()> {
+        check_rule(rule, level)?;
+        self.rules.insert(rule.to_string(), level);
+        Ok(())
+    }
+
+    /// Re-grades `diagnostics` according to the rule levels, dropping
+    /// allowed ones.
+    pub fn apply(&self, diagnostics: &mut Vec<Diagnostic>) {
+        diagnostics.retain_mut(|d| match self.rules.get(d.rule) {
+            None => true,
+            Some(Level::Allow) => false,
+            Some(Level::Warn) => {
+                d.severity = Severity::Warning;
+                true
+            }
+            Some(Level::Error) => {
+                d.severity = Severity::Error;
+                true
+            }
+        });
+    }
+}
+
+fn check_rule(rule: &str, level: Level) -> Result<()> {
+    if !RULES.iter().any(|(id, _)| *id == rule) {
+        let known: Vec<&str> = RULES.iter().map(|(id, _)| *id).collect();
+        anyhow::bail!("Unknown lint rule '{}' (known rules: {})", rule, known.join(", "));
+    }
+    // Files that don't parse or match the schema can't be checked any further
+    if (rule == RULE_JSON_SYNTAX || rule == RULE_SCHEMA) && level != Level::Error {
+        anyhow::bail!("Lint rule '{}' is always an error", rule);
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Reads a manifest. Relative paths in it are made relative to the
+    /// file's directory.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // The tools print errors without their cause chain, so keep the
+        // details in the message
+        let mut config: Config = toml::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+        for (rule, level) in &config.lint.rules {
+            check_rule(rule, *level).map_err(|e| anyhow!("{} in {}", e, path.display()))?;
+        }
+        if let Some(base) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            config.paths.rebase(base);
+            rebase_path(&mut config.bundle.image_cache, base);
+            rebase_path(&mut config.serve.dir, base);
+        }
+        Ok(config)
+    }
+
+    /// The configured viewports, or the built-in desktop and mobile ones if
+    /// the manifest declares none.
+    pub fn viewports(&self) -> Vec<Viewport> {
+        if self.viewports.is_empty() {
+            default_viewports()
+        } else {
+            self.viewports.clone()
+        }
+    }
+
+    /// Looks up a viewport by name.
+    pub fn viewport(&self, name: &str) -> Result<Viewport> {
+        let viewports = self.viewports();
+        let names: Vec<&str> = viewports.iter().map(|v| v.name.as_str()).collect();
+        let message = format!("Unknown viewport '{}' (configured: {})", name, names.join(", "));
+        viewports
+            .iter()
+            .find(|v| v.name == name)
+            .cloned()
+            .context(message)
+    }
+
+    /// The configuration as TOML, with derived defaults (the schema path,
+    /// the viewports) spelled out.
+    pub fn to_toml(&self) -> Result<String> {
+        let mut effective = self.clone();
+        effective.paths.schema = Some(self.paths.schema());
+        effective.viewports = self.viewports();
+        toml::to_string(&effective).context("Failed to serialize configuration")
+    }
+}
+
+/// The `--config` and `--print-config` flags every configurable tool takes.
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConfigArgs {
+    /// Project manifest to read. Defaults to ./game.toml if it exists
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration (manifest plus command-line
+    /// overrides) as TOML and exit
+    #[arg(long)]
+    pub print_config: bool,
+}
+
+impl ConfigArgs {
+    /// Loads `--config`, or `game.toml` if present, or the defaults.
+    pub fn load(&self) -> Result<Config> {
+        match &self.config {
+            Some(path) => Config::from_file(path),
+            None if Path::new(DEFAULT_CONFIG).exists() => Config::from_file(Path::new(DEFAULT_CONFIG)),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Prints `config` if `--print-config` was given. Returns whether it did,
+    /// in which case the tool should stop.
+    pub fn print(&self, config: &Config) -> Result<bool> {
+        if self.print_config {
+            print!("{}", config.to_toml()?);
+        }
+        Ok(self.print_config)
+    }
+}
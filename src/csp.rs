@@ -0,0 +1,535 @@
+//! Content Security Policy for the bundled page.
+//!
+//! The page is one HTML file whose scripts and styles are all inline, so the
+//! usual `script-src 'self'` would block the game itself, and
+//! `'unsafe-inline'` would let through anything that sneaks into the page
+//! from question text. [`secure_page`] instead hashes every inline `<script>`
+//! and `<style>` block of the rendered page and injects a
+//! `Content-Security-Policy` meta tag allowing exactly those blocks and
+//! nothing else.
+//!
+//! Inline `style="..."` attributes (the `display: none` of hidden screens)
+//! are allowed by hash via `'unsafe-hashes'`, which covers attributes with
+//! exactly that text only. Inline event handlers (`onclick="..."`) are
+//! rejected: attach them from script instead. Scripts and stylesheets loaded
+//! from files next to the page get a Subresource Integrity attribute.
+//!
+//! What the markup can't show, such as the service worker a script
+//! registers, the caller adds to the [`Policy`] it passes in.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// What went into the policy, for the build log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Hash sources of inline and local external scripts.
+    pub scripts: BTreeSet<String>,
+    /// Hash sources of inline `<style>` blocks and local stylesheets.
+    pub styles: BTreeSet<String>,
+    /// Hash sources of inline `style` attribute values.
+    pub style_attributes: BTreeSet<String>,
+    /// Remote origins scripts or stylesheets are loaded from, which can't be
+    /// pinned by hash at build time.
+    pub script_origins: BTreeSet<String>,
+    pub style_origins: BTreeSet<String>,
+    /// Whether scripts or stylesheets are loaded from files next to the page.
+    pub local_scripts: bool,
+    pub local_styles: bool,
+    /// Where the web app manifest of a `<link rel="manifest">` comes from.
+    pub manifests: BTreeSet<String>,
+    /// Sources service workers may be registered from. Not detected: set
+    /// by the caller.
+    pub workers: BTreeSet<String>,
+}
+
+impl Policy {
+    /// The policy as the `content` of a meta tag.
+    pub fn header_value(&self) -> String {
+        let sources = |hashes: &BTreeSet<String>, origins: &BTreeSet<String>, local: bool| {
+            let mut sources: Vec<String> = Vec::new();
+            if local {
+                sources.push("'self'".to_string());
+            }
+            sources.extend(hashes.iter().map(|h| format!("'{}'", h)));
+            sources.extend(origins.iter().cloned());
+            if sources.is_empty() {
+                sources.push("'none'".to_string());
+            }
+            sources.join(" ")
+        };
+        let mut style_src = sources(&self.styles, &self.style_origins, self.local_styles);
+        if !self.style_attributes.is_empty() {
+            style_src += " 'unsafe-hashes'";
+            for hash in &self.style_attributes {
+                style_src += &format!(" '{}'", hash);
+            }
+        }
+        let mut directives = vec![
+            "default-src 'none'".to_string(),
+            format!("script-src {}", sources(&self.scripts, &self.script_origins, self.local_scripts)),
+            format!("style-src {}", style_src),
+            // Images are files next to the page or data URIs (--inline-images)
+            "img-src 'self' data:".to_string(),
+            // ui.js fetches data/questions.json when nothing is embedded
+            "connect-src 'self'".to_string(),
+            "base-uri 'none'".to_string(),
+            "form-action 'none'".to_string(),
+        ];
+        for (directive, sources) in [("manifest-src", &self.manifests), ("worker-src", &self.workers)] {
+            if !sources.is_empty() {
+                let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+                directives.push(format!("{} {}", directive, sources.join(" ")));
+            }
+        }
+        directives.join("; ")
+    }
+}
+
+/// Hashes the inline code of `html` and injects a matching CSP meta tag at
+/// the top of `<head>`, allowing what it finds on top of `policy`. Relative
+/// `src`/`href` of scripts and stylesheets are resolved against `page_dir` to
+/// compute their integrity hashes.
+pub fn secure_page(html: &str, page_dir: &Path, mut policy: Policy) -> Result<(String, Policy)> {
+    // (offset, text) to insert into the page
+    let mut edits: Vec<(usize, String)> = Vec::new();
+    let mut head_end = None;
+    let lower = html.to_ascii_lowercase();
+    let mut pos = 0;
+
+    while let Some(found) = html[pos..].find('<') {
+        let start = pos + found;
+        if html[start..].starts_with("<!--") {
+            pos = html[start..].find("-->").map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+        let Some(tag) = parse_tag(html, start) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+
+        for (name, value) in &tag.attributes {
+            if name.starts_with("on") {
+                anyhow::bail!(
+                    "<{} {}=\"{}\"> is an inline event handler, which the Content-Security-Policy blocks; \
+                     attach it from a script instead",
+                    tag.name,
+                    name,
+                    value
+                );
+            }
+            if name == "style" {
+                policy.style_attributes.insert(hash_source(value.as_bytes()));
+            }
+        }
+
+        match tag.name.as_str() {
+            "head" if head_end.is_none() => head_end = Some(tag.end),
+            "meta" => {
+                if tag.attribute("charset").is_some() && head_end.is_some() {
+                    // Keep the charset declaration first
+                    head_end = Some(tag.end);
+                }
+                if tag
+                    .attribute("http-equiv")
+                    .is_some_and(|v| v.eq_ignore_ascii_case("content-security-policy"))
+                {
+                    anyhow::bail!(
+                        "The template already declares a Content-Security-Policy; remove it to let bundle generate one"
+                    );
+                }
+            }
+            "script" => {
+                let close = lower[tag.end..]
+                    .find("</script")
+                    .map(|i| tag.end + i)
+                    .context("<script> without </script>")?;
+                match tag.attribute("src") {
+                    Some(src) => external(
+                        src,
+                        &tag,
+                        page_dir,
+                        &mut policy.scripts,
+                        &mut policy.script_origins,
+                        &mut policy.local_scripts,
+                        &mut edits,
+                    )?,
+                    None => {
+                        policy.scripts.insert(hash_source(normalize_newlines(&html[tag.end..close]).as_bytes()));
+                    }
+                }
+                pos = close;
+            }
+            "style" => {
+                let close = lower[tag.end..]
+                    .find("</style")
+                    .map(|i| tag.end + i)
+                    .context("<style> without </style>")?;
+                policy.styles.insert(hash_source(normalize_newlines(&html[tag.end..close]).as_bytes()));
+                pos = close;
+            }
+            "link" => {
+                let rel = |name: &str| {
+                    tag.attribute("rel")
+                        .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case(name)))
+                };
+                if let (true, Some(href)) = (rel("manifest"), tag.attribute("href")) {
+                    policy
+                        .manifests
+                        .insert(remote_origin(href).unwrap_or_else(|| "'self'".to_string()));
+                }
+                if let (true, Some(href)) = (rel("stylesheet"), tag.attribute("href")) {
+                    external(
+                        href,
+                        &tag,
+                        page_dir,
+                        &mut policy.styles,
+                        &mut policy.style_origins,
+                        &mut policy.local_styles,
+                        &mut edits,
+                    )?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let head_end = head_end.context("The page has no <head> to put the Content-Security-Policy in")?;
+    edits.push((
+        head_end,
+        format!(
+            "\n    <meta http-equiv=\"Content-Security-Policy\" content=\"{}\">",
+            policy.header_value()
+        ),
+    ));
+
+    let mut page = html.to_string();
+    edits.sort_by_key(|(offset, _)| *offset);
+    for (offset, text) in edits.into_iter().rev() {
+        page.insert_str(offset, &text);
+    }
+    Ok((page, policy))
+}
+
+/// An opening tag.
+struct Tag {
+    name: String,
+    attributes: Vec<(String, String)>,
+    /// Offset of the `>` closing the tag (where new attributes go).
+    close: usize,
+    /// Offset just past the tag.
+    end: usize,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parses the opening tag at `start` (which is a `<`). Closing tags,
+/// doctypes and stray `<` yield `None`.
+fn parse_tag(html: &str, start: usize) -> Option<Tag> {
+    let bytes = html.as_bytes();
+    let name_len = html[start + 1..]
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(html.len() - start - 1);
+    if name_len == 0 || !bytes[start + 1].is_ascii_alphabetic() {
+        return None;
+    }
+    let name = html[start + 1..start + 1 + name_len].to_ascii_lowercase();
+    let mut attributes = Vec::new();
+    let mut i = start + 1 + name_len;
+
+    loop {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            return None;
+        }
+        if bytes[i] == b'>' {
+            return Some(Tag {
+                name,
+                attributes,
+                close: if bytes[i - 1] == b'/' { i - 1 } else { i },
+                end: i + 1,
+            });
+        }
+        let attr_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b"=>/".contains(&bytes[i]) {
+            i += 1;
+        }
+        let attr_name = html[attr_start..i].to_ascii_lowercase();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let mut value = String::new();
+        if i < bytes.len() && bytes[i] == b'=' {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            let (value_start, value_end) = match bytes.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let end = i + 1 + html[i + 1..].find(quote as char)?;
+                    let range = (i + 1, end);
+                    i = end + 1;
+                    range
+                }
+                _ => {
+                    let begin = i;
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                        i += 1;
+                    }
+                    (begin, i)
+                }
+            };
+            value = decode_entities(&html[value_start..value_end]);
+        }
+        if !attr_name.is_empty() {
+            attributes.push((attr_name, value));
+        }
+    }
+}
+
+/// Records a script or stylesheet loaded from `url`: files next to the page
+/// are hashed and given an `integrity` attribute, remote ones allowed by
+/// origin.
+fn external(
+    url: &str,
+    tag: &Tag,
+    page_dir: &Path,
+    hashes: &mut BTreeSet<String>,
+    origins: &mut BTreeSet<String>,
+    local: &mut bool,
+    edits: &mut Vec<(usize, String)>,
+) -> Result<()> {
+    if let Some(origin) = remote_origin(url) {
+        origins.insert(origin);
+        return Ok(());
+    }
+    let path = page_dir.join(url.split(['?', '#']).next().unwrap_or(url));
+    let content = fs::read(&path)
+        .with_context(|| format!("Failed to read {} to compute its integrity hash", path.display()))?;
+    let hash = hash_source(&content);
+    *local = true;
+    if tag.attribute("integrity").is_none() {
+        edits.push((tag.close, format!(" integrity=\"{}\"", hash)));
+    }
+    hashes.insert(hash);
+    Ok(())
+}
+
+/// `https://cdn.example.com` for `https://cdn.example.com/x.js`; `None` for
+/// relative URLs.
+fn remote_origin(url: &str) -> Option<String> {
+    let url = if let Some(rest) = url.strip_prefix("//") {
+        format!("https://{}", rest)
+    } else if url.contains("://") {
+        url.to_string()
+    } else {
+        return None;
+    };
+    let (scheme, rest) = url.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    Some(format!("{}://{}", scheme, host))
+}
+
+/// A CSP hash source, e.g. `sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=`.
+pub fn hash_source(content: &[u8]) -> String {
+    format!("sha256-{}", BASE64.encode(Sha256::digest(content)))
+}
+
+/// Browsers hash script text after the HTML parser has turned CRLF and CR
+/// into LF.
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hashes computed independently: base64 of the SHA-256 digest
+    const ALERT: &str = "sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=";
+    const BODY_RED: &str = "sha256-XeYlw2NVzOfB1UCIJqCyGr+0n7bA4fFslFpvKu84IAw=";
+    const DISPLAY_NONE: &str = "sha256-biLFinpqYMtWHmXfkA1BPeCY0/fNt46SAZ+BBk5YUog=";
+
+    fn secure(html: &str) -> Result<(String, Policy)> {
+        secure_page(html, Path::new("."), Policy::default())
+    }
+
+    /// The `content` of the page's CSP meta tag.
+    fn csp(page: &str) -> &str {
+        let start = page.find("http-equiv=\"Content-Security-Policy\" content=\"").unwrap() + 46;
+        &page[start..start + page[start..].find('"').unwrap()]
+    }
+
+    fn document(head: &str, body: &str) -> String {
+        format!("<!DOCTYPE html>\n<html>\n<head>\n{}\n</head>\n<body>\n{}\n</body>\n</html>\n", head, body)
+    }
+
+    #[test]
+    fn hashes_inline_scripts_and_styles_into_the_policy() {
+        let (page, policy) = secure(&document(
+            "<style>body { color: red; }</style>",
+            "<script>alert(1)</script>",
+        ))
+        .unwrap();
+        assert_eq!(policy.scripts, BTreeSet::from([ALERT.to_string()]));
+        assert_eq!(policy.styles, BTreeSet::from([BODY_RED.to_string()]));
+        assert_eq!(
+            csp(&page),
+            format!(
+                "default-src 'none'; script-src '{}'; style-src '{}'; img-src 'self' data:; \
+                 connect-src 'self'; base-uri 'none'; form-action 'none'",
+                ALERT, BODY_RED
+            )
+        );
+    }
+
+    #[test]
+    fn hashes_scripts_as_the_browser_sees_them() {
+        // The HTML parser turns CRLF into LF before the browser hashes
+        let (_, policy) = secure(&document("", "<script>a\r\nb</script>")).unwrap();
+        assert!(policy.scripts.contains("sha256-fhj3NzEbLcOy8mndeDlrA1HxT7Zu+oefdoyyMYGIPHg="));
+        assert_eq!(hash_source(b""), "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=");
+    }
+
+    #[test]
+    fn rejects_inline_event_handlers() {
+        let e = secure(&document("", "<button ONCLICK=\"go()\">Go</button>")).unwrap_err();
+        assert!(e.to_string().contains("<button onclick=\"go()\"> is an inline event handler"));
+    }
+
+    #[test]
+    fn rejects_an_existing_policy() {
+        let e = secure(&document(
+            "<meta http-equiv=\"content-security-policy\" content=\"default-src *\">",
+            "",
+        ))
+        .unwrap_err();
+        assert!(e.to_string().contains("already declares a Content-Security-Policy"));
+    }
+
+    #[test]
+    fn places_the_policy_after_the_charset() {
+        let (page, _) = secure(&document("<meta charset=\"UTF-8\">\n<title>Quiz</title>", "")).unwrap();
+        let charset = page.find("<meta charset=\"UTF-8\">").unwrap();
+        let policy = page.find("<meta http-equiv=\"Content-Security-Policy\"").unwrap();
+        assert_eq!(&page[charset + 22..policy], "\n    ");
+        assert!(policy < page.find("<title>").unwrap());
+
+        // Without a charset it opens <head>
+        let (page, _) = secure(&document("<title>Quiz</title>", "")).unwrap();
+        assert!(page.contains("<head>\n    <meta http-equiv=\"Content-Security-Policy\""));
+    }
+
+    #[test]
+    fn requires_a_head() {
+        assert!(secure("<body><script>alert(1)</script></body>").is_err());
+    }
+
+    #[test]
+    fn allows_style_attributes_by_hash() {
+        let (page, policy) = secure(&document("", "<div style=\"display: none;\"></div><p style='display: none;'></p>")).unwrap();
+        assert_eq!(policy.style_attributes, BTreeSet::from([DISPLAY_NONE.to_string()]));
+        assert!(csp(&page).contains(&format!("style-src 'none' 'unsafe-hashes' '{}'", DISPLAY_NONE)));
+    }
+
+    #[test]
+    fn decodes_attribute_entities_before_hashing() {
+        let (_, policy) = secure(&document("", "<div style=\"content: &quot;a&quot;\"></div>")).unwrap();
+        assert_eq!(policy.style_attributes, BTreeSet::from([hash_source(b"content: \"a\"")]));
+    }
+
+    #[test]
+    fn adds_integrity_to_local_scripts_and_stylesheets() {
+        let dir = std::env::temp_dir().join(format!("csp-sri-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app.js"), "console.log(\"local\");\n").unwrap();
+        fs::write(dir.join("style.css"), "h1 { margin: 0 }\n").unwrap();
+        let html = document(
+            "<link rel=\"stylesheet\" href=\"style.css?v=2\">\n<link rel=\"stylesheet\" href=\"https://cdn.example.com/x.css\">",
+            "<script src=\"app.js\"></script>\n<script src=\"//cdn.example.com/y.js\"></script>",
+        );
+        let (page, policy) = secure_page(&html, &dir, Policy::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let js = "sha256-m7GbCKy2G79qU7V+DsOOauZ9KKfp+AvNvzL88V7FOr0=";
+        let css = "sha256-tWV/10ct6sTgviosevWiNXst+h443ZNJAHcNMaftGU4=";
+        assert!(page.contains(&format!("<script src=\"app.js\" integrity=\"{}\"></script>", js)));
+        assert!(page.contains(&format!("<link rel=\"stylesheet\" href=\"style.css?v=2\" integrity=\"{}\">", css)));
+        // Remote files are allowed by origin, not pinned
+        assert!(page.contains("<script src=\"//cdn.example.com/y.js\"></script>"));
+        assert!(csp(&page).contains(&format!("script-src 'self' '{}' https://cdn.example.com;", js)));
+        assert!(csp(&page).contains(&format!("style-src 'self' '{}' https://cdn.example.com;", css)));
+        assert!(policy.local_scripts && policy.local_styles);
+    }
+
+    #[test]
+    fn fails_on_a_missing_local_file() {
+        let e = secure(&document("", "<script src=\"missing-file.js\"></script>")).unwrap_err();
+        assert!(e.to_string().contains("missing-file.js"));
+    }
+
+    #[test]
+    fn skips_comments_and_script_text() {
+        let html = document(
+            "<!-- <meta http-equiv=\"Content-Security-Policy\"> <b onclick=\"x\"> -->",
+            "<script>\nif (a < b) { go(); }\n</script>\n\
+             <script>const s = '<div onclick=\"nope\">';</script>\n\
+             <p>1 < 2 and <3</p>",
+        );
+        let (_, policy) = secure(&html).unwrap();
+        assert!(policy.scripts.contains("sha256-akGGGuINv5HVdfoT6LqIGlNK5KO/nEvTLIT3K0o65os="));
+        assert_eq!(policy.scripts.len(), 2);
+    }
+
+    #[test]
+    fn parses_tags_and_attributes() {
+        let html = "<INPUT type=checkbox checked data-x = 'a > b' />";
+        let tag = parse_tag(html, 0).unwrap();
+        assert_eq!(tag.name, "input");
+        assert_eq!(tag.attribute("type"), Some("checkbox"));
+        assert_eq!(tag.attribute("checked"), Some(""));
+        assert_eq!(tag.attribute("data-x"), Some("a > b"));
+        assert_eq!(tag.end, html.len());
+        assert_eq!(&html[tag.close..tag.close + 1], "/");
+        assert!(parse_tag("</div>", 0).is_none());
+        assert!(parse_tag("<!DOCTYPE html>", 0).is_none());
+        assert!(parse_tag("< 3", 0).is_none());
+        assert!(parse_tag("<div class=\"open", 0).is_none());
+    }
+
+    #[test]
+    fn reports_manifests_and_workers() {
+        let mut policy = Policy::default();
+        policy.workers.insert("'self'".to_string());
+        let (page, _) = secure_page(&document("<link rel=\"manifest\" href=\"app.webmanifest\">", ""), Path::new("."), policy).unwrap();
+        assert!(csp(&page).ends_with("form-action 'none'; manifest-src 'self'; worker-src 'self'"));
+    }
+}
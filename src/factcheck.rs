@@ -0,0 +1,384 @@
+//! Fact-checks predator/prey questions against the [`FoodWeb`] graph.
+//!
+//! Many easy-mode questions encode a claim about who eats whom, e.g.
+//! `kids_1`: "The animal above is eaten by which animal below?" with
+//! `image1: KR.png` and the answer "Penguins". The question's subject is
+//! resolved from its `image1` code (`KR`), the choices are matched to graph
+//! nodes by name, and the claim is checked in both directions: the correct
+//! choice must satisfy it and no distractor may satisfy it as well.
+//!
+//! Questions whose wording or choices cannot be resolved (numeric answers,
+//! "Whales" when there are two kinds of whale, ...) are skipped.
+
+use crate::foodweb::{FoodWeb, Node};
+use crate::model::{Question, QuestionSetFile};
+use crate::validation::{
+    Diagnostic, Severity, RULE_FOODWEB_CONTRADICTION, RULE_FOODWEB_DISTRACTOR,
+};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Which way round a question asks about the subject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// "eaten by which animal", "food for which predator": the answer eats
+    /// the subject.
+    PredatorOfSubject,
+    /// "What does this animal eat?", "main prey": the subject eats the answer.
+    PreyOfSubject,
+}
+
+/// The predator/prey claim a question makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Claim {
+    pub relation: Relation,
+    /// "Which predator does NOT eat this prey?"
+    pub negated: bool,
+}
+
+/// Works out from the wording whether a question is about predators or prey.
+///
+/// The subject is whatever the question points at ("this animal", "the fish
+/// shown above") or, failing that, names outright; the answer is what the
+/// "which"/"what" stands for. So "The animal above is eaten by which animal?"
+/// asks for a predator while "Which of these is NOT eaten by penguins?" asks
+/// for something penguins don't eat.
+pub fn classify(question: &str) -> Option<Claim> {
+    // "Which of these ..." points at the choices, not the subject
+    let text = question.to_lowercase().replace("which of these", "which");
+    let negated = [" not ", "n't ", " never "].iter().any(|p| text.contains(p));
+    let claim = |relation| Some(Claim { relation, negated });
+
+    if let Some(pos) = text.find("eaten by") {
+        let asks_for_eater = ["which", "what"].iter().any(|w| text[pos..].contains(w));
+        return claim(if asks_for_eater {
+            Relation::PredatorOfSubject
+        } else {
+            Relation::PreyOfSubject
+        });
+    }
+    if text.contains("food for") {
+        return claim(Relation::PredatorOfSubject);
+    }
+
+    // Active phrasings: whoever comes before the verb does the eating
+    let verb = [" eat", " feed on", " catch"]
+        .iter()
+        .filter_map(|v| text.find(v))
+        .min();
+    let Some(verb) = verb else {
+        // "The whale above has teeth. What is its main prey?"
+        if text.contains("prey?") || text.contains("main prey") {
+            return claim(Relation::PreyOfSubject);
+        }
+        return None;
+    };
+    let points_at_subject = |part: &str| ["this", "these", "above"].iter().any(|r| part.contains(r));
+    if points_at_subject(&text[..verb]) || text.starts_with("what do") {
+        claim(Relation::PreyOfSubject)
+    } else if points_at_subject(&text[verb..]) || text.starts_with("which") || text.starts_with("what") {
+        claim(Relation::PredatorOfSubject)
+    } else {
+        // e.g. "How many penguins can a leopard seal eat in one day?"
+        None
+    }
+}
+
+/// The node for an image reference such as `KR.png` (the file stem is the
+/// node id).
+pub fn resolve_image<'a>(web: &'a FoodWeb, image: &str) -> Option<&'a Node> {
+    let stem = Path::new(image).file_stem()?.to_str()?;
+    web.node(stem)
+}
+
+/// The node a choice such as "Penguins" or "Large fish" refers to, if exactly
+/// one node matches.
+///
+/// A choice matches a node when all words of the node's name appear in the
+/// choice ("Large fish" → Fish) or all words of the choice appear in the
+/// node's full label ("Phytoplankton" → "Primary production / phytoplankton
+/// & ice algae"). Words are compared case-insensitively and singularised.
+pub fn resolve_choice<'a>(web: &'a FoodWeb, choice: &str) -> Option<&'a Node> {
+    let choice_words = words(choice);
+    if choice_words.is_empty() {
+        return None;
+    }
+    let mut matches = web.nodes().iter().filter(|node| {
+        let name_words = words(node.name());
+        let label_words = words(&node.label);
+        (!name_words.is_empty() && name_words.is_subset(&choice_words))
+            || choice_words.is_subset(&label_words)
+    });
+    let first = matches.next()?;
+    match matches.next() {
+        Some(_) => None,
+        None => Some(first),
+    }
+}
+
+/// Checks every question that makes a resolvable predator/prey claim.
+pub fn check_food_web(files: &[QuestionSetFile], web: &FoodWeb) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for file in files {
+        for (q_idx, question) in file.set.questions.iter().enumerate() {
+            check_question(file, q_idx, question, web, &mut diagnostics);
+        }
+    }
+    diagnostics
+}
+
+fn check_question(
+    file: &QuestionSetFile,
+    q_idx: usize,
+    question: &Question,
+    web: &FoodWeb,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(claim) = classify(&question.question) else {
+        return;
+    };
+    let Some(subject) = question.image1.as_deref().and_then(|i| resolve_image(web, i)) else {
+        return;
+    };
+
+    // Does `candidate` satisfy the claim about `subject`?
+    let satisfies = |candidate: &Node| {
+        let related = match claim.relation {
+            Relation::PredatorOfSubject => web.eats(&candidate.id, &subject.id),
+            Relation::PreyOfSubject => web.eats(&subject.id, &candidate.id),
+        };
+        related != claim.negated
+    };
+    let describe = |candidate: &Node| {
+        let (predator, prey) = match claim.relation {
+            Relation::PredatorOfSubject => (candidate, subject),
+            Relation::PreyOfSubject => (subject, candidate),
+        };
+        let verb = if web.eats(&predator.id, &prey.id) { "eat" } else { "do not eat" };
+        format!(
+            "{} ({}) {} {} ({})",
+            predator.name(),
+            predator.id,
+            verb,
+            prey.name(),
+            prey.id
+        )
+    };
+    let mut report = |field: String, rule, severity, message| {
+        diagnostics.push(Diagnostic::new(
+            &file.path,
+            Some(&question.id),
+            format!("/questions/{}/{}", q_idx, field),
+            rule,
+            severity,
+            message,
+        ));
+    };
+
+    let correct = question
+        .correct_choice()
+        .and_then(|c| resolve_choice(web, c));
+    if let Some(correct) = correct {
+        if !satisfies(correct) {
+            report(
+                "correctAnswer".to_string(),
+                RULE_FOODWEB_CONTRADICTION,
+                Severity::Error,
+                format!(
+                    "correct answer '{}' contradicts the food web: {}",
+                    question.choices[question.correct_answer],
+                    describe(correct)
+                ),
+            );
+        }
+    }
+
+    let mut reported = HashSet::new();
+    for (c_idx, choice) in question.choices.iter().enumerate() {
+        if c_idx == question.correct_answer {
+            continue;
+        }
+        let Some(node) = resolve_choice(web, choice) else {
+            continue;
+        };
+        // A distractor naming the same organism as the answer is not a second answer
+        if Some(&node.id) == correct.map(|c| &c.id) || !reported.insert(&node.id) {
+            continue;
+        }
+        if satisfies(node) {
+            report(
+                format!("choices/{}", c_idx),
+                RULE_FOODWEB_DISTRACTOR,
+                Severity::Warning,
+                format!(
+                    "distractor '{}' is also a correct answer: {}",
+                    choice,
+                    describe(node)
+                ),
+            );
+        }
+    }
+}
+
+/// Lowercased, singularised words, ignoring punctuation such as `&`.
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let w = w.to_lowercase();
+            match w.strip_suffix('s') {
+                Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem.to_string(),
+                _ => w,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Metadata, Mode, QuestionSet};
+    use std::path::PathBuf;
+
+    fn web() -> FoodWeb {
+        FoodWeb::from_mermaid(
+            "flowchart BT\n\
+             PP((\"Primary production\\nphytoplankton & ice algae\")) --> KR[\"Krill\"] & ZP[\"Zooplankton\"]\n\
+             KR --> PG[\"Penguins\"] & BW[\"Baleen whales\"] & FI[\"Fish\"]\n\
+             ZP --> FI & SQ[\"Squid\"]\n\
+             FI --> PG & SE[\"Seals\"] & TW[\"Toothed whales\"]\n\
+             SQ --> SE & TW",
+        )
+        .unwrap()
+    }
+
+    fn claim(relation: Relation, negated: bool) -> Option<Claim> {
+        Some(Claim { relation, negated })
+    }
+
+    #[test]
+    fn classifies_questions_asking_for_a_predator() {
+        for question in [
+            "The animal above is eaten by which animal below?",
+            "The squid above is food for which predator?",
+            "Which animal eats the fish shown above?",
+        ] {
+            assert_eq!(classify(question), claim(Relation::PredatorOfSubject, false), "{}", question);
+        }
+        assert_eq!(
+            classify("Which large predator does NOT eat this prey?"),
+            claim(Relation::PredatorOfSubject, true)
+        );
+    }
+
+    #[test]
+    fn classifies_questions_asking_for_prey() {
+        for question in [
+            "What does this animal eat?",
+            "What do these giant whales eat?",
+            "These bottom-dwelling creatures feed on what?",
+            "The whale above has teeth. What is its main prey?",
+        ] {
+            assert_eq!(classify(question), claim(Relation::PreyOfSubject, false), "{}", question);
+        }
+        assert_eq!(
+            classify("Which of these is NOT eaten by penguins?"),
+            claim(Relation::PreyOfSubject, true)
+        );
+    }
+
+    #[test]
+    fn classifies_questions_about_producers() {
+        assert_eq!(
+            classify("The tiny plants above are food for which animal?"),
+            claim(Relation::PredatorOfSubject, false)
+        );
+    }
+
+    #[test]
+    fn skips_questions_that_are_not_about_the_food_web() {
+        for question in [
+            "How many penguins can a leopard seal eat in one day?",
+            "Why are phytoplankton called 'primary producers'?",
+            "Emperor penguins can fast for up to how many days during breeding?",
+        ] {
+            assert_eq!(classify(question), None, "{}", question);
+        }
+    }
+
+    #[test]
+    fn resolves_choices_by_name_or_label() {
+        let web = web();
+        let id = |choice| resolve_choice(&web, choice).map(|n| n.id.as_str());
+        assert_eq!(id("Penguins"), Some("PG"));
+        assert_eq!(id("Large fish"), Some("FI"));
+        assert_eq!(id("Phytoplankton"), Some("PP"));
+        assert_eq!(id("ice algae"), Some("PP"));
+        assert_eq!(resolve_image(&web, "KR.png").map(|n| n.id.as_str()), Some("KR"));
+    }
+
+    #[test]
+    fn does_not_resolve_ambiguous_or_unknown_choices() {
+        let web = web();
+        // Baleen and toothed whales
+        assert!(resolve_choice(&web, "Whales").is_none());
+        assert!(resolve_choice(&web, "Polar bears").is_none());
+        assert!(resolve_choice(&web, "42").is_none());
+        assert!(resolve_choice(&web, "").is_none());
+        assert!(resolve_image(&web, "XX.png").is_none());
+    }
+
+    #[test]
+    fn checks_answers_and_distractors_against_the_web() {
+        let question = Question {
+            id: "kids_9".to_string(),
+            question: "The tiny plants above are food for which animal?".to_string(),
+            image1: Some("PP.png".to_string()),
+            image2: None,
+            choices: vec!["Penguins".to_string(), "Krill".to_string(), "Whales".to_string()],
+            correct_answer: 0,
+            explanation: "Krill graze on phytoplankton.".to_string(),
+            category: "food-chain".to_string(),
+            difficulty: None,
+            tags: Vec::new(),
+        };
+        let file = QuestionSetFile {
+            key: "questions_easy".to_string(),
+            path: PathBuf::from("questions_easy.json"),
+            set: QuestionSet {
+                metadata: Metadata {
+                    title: "Ross Sea Food Web".to_string(),
+                    description: None,
+                    version: "1.0.0".to_string(),
+                    target_age: "5-8 years".to_string(),
+                    subject: "Marine Biology".to_string(),
+                    mode: Mode::Easy,
+                },
+                questions: vec![question],
+            },
+        };
+        let diagnostics = check_food_web(&[file], &web());
+        let findings: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.rule, d.severity, d.pointer.as_str(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    RULE_FOODWEB_CONTRADICTION,
+                    Severity::Error,
+                    "/questions/0/correctAnswer",
+                    "correct answer 'Penguins' contradicts the food web: \
+                     Penguins (PG) do not eat Primary production (PP)",
+                ),
+                (
+                    RULE_FOODWEB_DISTRACTOR,
+                    Severity::Warning,
+                    "/questions/0/choices/1",
+                    "distractor 'Krill' is also a correct answer: Krill (KR) eat Primary production (PP)",
+                ),
+            ]
+        );
+    }
+}
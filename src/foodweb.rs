@@ -0,0 +1,453 @@
+//! The Ross Sea food web as a typed graph.
+//!
+//! The canonical predator/prey relationships are drawn as a mermaid
+//! `flowchart BT` in README.md, where `KR --> PG` means energy flows from
+//! krill to penguins, i.e. penguins eat krill. Dashed edges (`FI -.-> BD`)
+//! are detritus flows: dead organisms becoming bacteria & detritus.
+//!
+//! [`FoodWeb::load`] reads either that README (or any markdown file with a
+//! mermaid block), a bare `.mmd` file, or a `foodweb.json` holding the
+//! serialized graph.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// An organism (or human activity, e.g. fishing) in the food web.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Node {
+    /// Short code, matching the image asset name (`KR` for `KR.png`).
+    pub id: String,
+    /// Display label. May contain line breaks, e.g.
+    /// "Primary production\nphytoplankton & ice algae".
+    pub label: String,
+}
+
+impl Node {
+    /// The first line of the label, e.g. "Primary production".
+    pub fn name(&self) -> &str {
+        self.label.lines().next().unwrap_or(&self.id)
+    }
+}
+
+/// What an edge in the diagram stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// Solid arrow: `to` eats `from`.
+    Feeding,
+    /// Dashed arrow: dead `from` becomes part of `to` (bacteria & detritus).
+    Detritus,
+}
+
+/// A directed edge in the direction energy flows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    /// Food source.
+    pub from: String,
+    /// Consumer.
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// The food-web graph: nodes in order of first appearance, and edges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoodWeb {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl FoodWeb {
+    /// Loads the graph from `path`: a `.json` file with `nodes` and `edges`,
+    /// a `.mmd` mermaid file, or a markdown file containing a mermaid block.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&content).map_err(anyhow::Error::from),
+            Some("mmd") => Self::from_mermaid(&content),
+            _ => Self::from_markdown(&content),
+        }
+        .with_context(|| format!("Failed to read food web from {}", path.display()))
+    }
+
+    /// Parses the first mermaid code block in a markdown document.
+    pub fn from_markdown(markdown: &str) -> Result<Self> {
+        let mut lines = markdown.lines();
+        lines
+            .by_ref()
+            .find(|l| l.trim_start().starts_with("```mermaid"))
+            .context("No ```mermaid block found")?;
+        let block: Vec<&str> = lines.take_while(|l| !l.trim_start().starts_with("```")).collect();
+        Self::from_mermaid(&block.join("\n"))
+    }
+
+    /// Parses a mermaid `flowchart`/`graph` diagram. Node definitions
+    /// (`KR["Krill"]`, `PP(("..."))`, ...), `&` groups and solid (`-->`) or
+    /// dashed (`-.->`) arrows are understood; styling statements are ignored.
+    pub fn from_mermaid(source: &str) -> Result<Self> {
+        let mut web = FoodWeb::default();
+        let mut in_front_matter = false;
+        let mut seen_header = false;
+
+        for (idx, raw) in source.lines().enumerate() {
+            let line = raw.trim();
+            if line == "---" {
+                in_front_matter = !in_front_matter;
+                continue;
+            }
+            if in_front_matter || line.is_empty() || line.starts_with("%%") {
+                continue;
+            }
+            if !seen_header {
+                if line.starts_with("flowchart") || line.starts_with("graph") {
+                    seen_header = true;
+                    continue;
+                }
+                anyhow::bail!("line {}: expected a flowchart header, found '{}'", idx + 1, line);
+            }
+            let keyword = line.split_whitespace().next().unwrap_or("");
+            if matches!(
+                keyword,
+                "classDef" | "class" | "style" | "linkStyle" | "click" | "subgraph" | "end" | "direction"
+            ) {
+                continue;
+            }
+            for statement in line.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+                web.parse_statement(statement)
+                    .with_context(|| format!("line {}: '{}'", idx + 1, statement))?;
+            }
+        }
+
+        if !seen_header {
+            anyhow::bail!("No flowchart found in mermaid source");
+        }
+        Ok(web)
+    }
+
+    /// All nodes, in order of first appearance.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// All edges, in diagram order.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    /// Whether `predator` eats `prey` (a solid edge `prey --> predator`).
+    pub fn eats(&self, predator: &str, prey: &str) -> bool {
+        self.edges
+            .iter()
+            .any(|e| e.kind == EdgeKind::Feeding && e.from == prey && e.to == predator)
+    }
+
+    /// What `id` eats.
+    pub fn prey_of<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        self.feeding()
+            .filter(move |e| e.to == id)
+            .filter_map(|e| self.node(&e.from))
+    }
+
+    /// What eats `id`.
+    pub fn predators_of<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        self.feeding()
+            .filter(move |e| e.from == id)
+            .filter_map(|e| self.node(&e.to))
+    }
+
+    /// Trophic level of every node that can be reached from a producer:
+    /// producers (nodes that eat nothing) are level 1, everything else is one
+    /// above its lowest-level prey, i.e. the length of the shortest food chain
+    /// leading to it. Nodes caught in a cycle with no producer below are left out.
+    pub fn trophic_levels(&self) -> HashMap<&str, usize> {
+        let mut levels: HashMap<&str, usize> = self
+            .nodes
+            .iter()
+            .filter(|n| self.prey_of(&n.id).next().is_none())
+            .map(|n| (n.id.as_str(), 1))
+            .collect();
+        let mut frontier: Vec<&str> = levels.keys().copied().collect();
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for id in frontier {
+                let level = levels[id] + 1;
+                for predator in self.predators_of(id) {
+                    if !levels.contains_key(predator.id.as_str()) {
+                        levels.insert(&predator.id, level);
+                        next.push(predator.id.as_str());
+                    }
+                }
+            }
+            frontier = next;
+        }
+        levels
+    }
+
+    fn feeding(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(|e| e.kind == EdgeKind::Feeding)
+    }
+
+    /// Parses `A & B --> C["Label"] & D -.-> E`.
+    fn parse_statement(&mut self, statement: &str) -> Result<()> {
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut arrows: Vec<EdgeKind> = Vec::new();
+        let mut rest = statement;
+
+        loop {
+            let mut group = Vec::new();
+            loop {
+                let (id, after) = self.parse_node(rest)?;
+                group.push(id);
+                rest = after.trim_start();
+                match rest.strip_prefix('&') {
+                    Some(after) => rest = after.trim_start(),
+                    None => break,
+                }
+            }
+            groups.push(group);
+
+            if rest.is_empty() {
+                break;
+            }
+            let (kind, after) = parse_arrow(rest)?;
+            arrows.push(kind);
+            rest = after.trim_start();
+        }
+
+        for (pair, kind) in groups.windows(2).zip(arrows) {
+            for from in &pair[0] {
+                for to in &pair[1] {
+                    let edge = Edge {
+                        from: from.clone(),
+                        to: to.clone(),
+                        kind,
+                    };
+                    if !self.edges.contains(&edge) {
+                        self.edges.push(edge);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a node reference with an optional shape and label, registering
+    /// the node. Returns its id and the remaining input.
+    fn parse_node<'a>(&mut self, input: &'a str) -> Result<(String, &'a str)> {
+        let input = input.trim_start();
+        let id_len = input
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(input.len());
+        if id_len == 0 {
+            anyhow::bail!("expected a node id at '{}'", input);
+        }
+        let (id, mut rest) = input.split_at(id_len);
+
+        let mut label = None;
+        const SHAPES: &[(&str, &str)] = &[
+            ("(((", ")))"),
+            ("((", "))"),
+            ("[(", ")]"),
+            ("([", "])"),
+            ("[[", "]]"),
+            ("{{", "}}"),
+            ("[/", "/]"),
+            ("[\\", "\\]"),
+            ("[", "]"),
+            ("(", ")"),
+            ("{", "}"),
+            (">", "]"),
+        ];
+        if let Some((open, close)) = SHAPES.iter().find(|(open, _)| rest.starts_with(open)) {
+            let inner = &rest[open.len()..];
+            let (text, after) = match inner.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"').context("unterminated quoted label")?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                None => {
+                    let end = inner.find(close).context("unterminated node shape")?;
+                    (&inner[..end], &inner[end..])
+                }
+            };
+            rest = after
+                .strip_prefix(close)
+                .with_context(|| format!("expected '{}' after label of {}", close, id))?;
+            label = Some(text.replace("\\n", "\n").trim().to_string());
+        }
+
+        match self.nodes.iter_mut().find(|n| n.id == id) {
+            Some(node) => {
+                if let Some(label) = label {
+                    node.label = label;
+                }
+            }
+            None => self.nodes.push(Node {
+                id: id.to_string(),
+                label: label.unwrap_or_else(|| id.to_string()),
+            }),
+        }
+        Ok((id.to_string(), rest))
+    }
+}
+
+/// Parses an arrow, including an optional `|label|`, and classifies it.
+fn parse_arrow(input: &str) -> Result<(EdgeKind, &str)> {
+    const ARROWS: &[(&str, EdgeKind)] = &[
+        ("-.->", EdgeKind::Detritus),
+        ("-.-", EdgeKind::Detritus),
+        ("==>", EdgeKind::Feeding),
+        ("-->", EdgeKind::Feeding),
+        ("---", EdgeKind::Feeding),
+    ];
+    let (arrow, kind) = ARROWS
+        .iter()
+        .find(|(arrow, _)| input.starts_with(arrow))
+        .with_context(|| format!("expected an arrow at '{}'", input))?;
+    let mut rest = input[arrow.len()..].trim_start();
+    if let Some(labelled) = rest.strip_prefix('|') {
+        let end = labelled.find('|').context("unterminated edge label")?;
+        rest = &labelled[end + 1..];
+    }
+    Ok((*kind, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn web(body: &str) -> FoodWeb {
+        FoodWeb::from_mermaid(&format!("flowchart BT\n{}", body)).unwrap()
+    }
+
+    fn edges(web: &FoodWeb) -> Vec<(&str, &str, EdgeKind)> {
+        web.edges().iter().map(|e| (e.from.as_str(), e.to.as_str(), e.kind)).collect()
+    }
+
+    #[test]
+    fn tells_solid_from_dashed_edges() {
+        let web = web("KR --> PG\nPG -.-> BD\nPG -.- BD2\nFI ==> SE\nZP --- SQ");
+        assert_eq!(
+            edges(&web),
+            vec![
+                ("KR", "PG", EdgeKind::Feeding),
+                ("PG", "BD", EdgeKind::Detritus),
+                ("PG", "BD2", EdgeKind::Detritus),
+                ("FI", "SE", EdgeKind::Feeding),
+                ("ZP", "SQ", EdgeKind::Feeding),
+            ]
+        );
+        assert!(web.eats("PG", "KR"));
+        // Detritus flows are not feeding
+        assert!(!web.eats("BD", "PG"));
+    }
+
+    #[test]
+    fn reads_node_labels_in_any_shape() {
+        let web = web(
+            "PP((\"Primary production\\nphytoplankton & ice algae\")) --> BD[(\"Bacteria & detritus\")]\n\
+             KR[Krill] --> PG{{Penguins}}\nPG --> SE(Seals)",
+        );
+        let label = |id| web.node(id).unwrap().label.clone();
+        assert_eq!(label("PP"), "Primary production\nphytoplankton & ice algae");
+        assert_eq!(web.node("PP").unwrap().name(), "Primary production");
+        assert_eq!(label("BD"), "Bacteria & detritus");
+        assert_eq!(label("KR"), "Krill");
+        assert_eq!(label("PG"), "Penguins");
+        assert_eq!(label("SE"), "Seals");
+    }
+
+    #[test]
+    fn labels_nodes_defined_after_their_first_use() {
+        let web = web("KR --> PG\nPG[\"Penguins\"]");
+        assert_eq!(web.node("KR").unwrap().label, "KR");
+        assert_eq!(web.node("PG").unwrap().label, "Penguins");
+        assert_eq!(web.nodes().len(), 2);
+        assert_eq!(web.edges().len(), 1);
+    }
+
+    #[test]
+    fn expands_chained_edges_and_groups() {
+        let web = web("PP --> ZP --> SF -.-> BD\nKR & ZP --> SQ & FI");
+        assert_eq!(
+            edges(&web),
+            vec![
+                ("PP", "ZP", EdgeKind::Feeding),
+                ("ZP", "SF", EdgeKind::Feeding),
+                ("SF", "BD", EdgeKind::Detritus),
+                ("KR", "SQ", EdgeKind::Feeding),
+                ("KR", "FI", EdgeKind::Feeding),
+                ("ZP", "SQ", EdgeKind::Feeding),
+                ("ZP", "FI", EdgeKind::Feeding),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_comments_front_matter_styling_and_edge_labels() {
+        let source = "---\nconfig:\n  layout: elk\n---\n%% header comment\nflowchart BT\n    %% KR --> XX\n    \
+                      classDef prey fill:#fff\n    style KR fill:#f9f\n    KR -->|eaten by| PG; PG --> SE\n";
+        let web = FoodWeb::from_mermaid(source).unwrap();
+        assert_eq!(
+            edges(&web),
+            vec![("KR", "PG", EdgeKind::Feeding), ("PG", "SE", EdgeKind::Feeding)]
+        );
+        assert!(web.node("XX").is_none());
+    }
+
+    #[test]
+    fn deduplicates_repeated_edges() {
+        let web = web("KR --> PG\nKR --> PG & SE");
+        assert_eq!(web.edges().len(), 2);
+    }
+
+    #[test]
+    fn rejects_malformed_diagrams() {
+        assert!(FoodWeb::from_mermaid("KR --> PG").is_err());
+        assert!(FoodWeb::from_mermaid("%% only a comment").is_err());
+        assert!(FoodWeb::from_mermaid("flowchart BT\nKR ~~> PG").is_err());
+        assert!(FoodWeb::from_mermaid("flowchart BT\nKR[\"Krill --> PG").is_err());
+        assert!(FoodWeb::from_mermaid("flowchart BT\nKR -->").is_err());
+    }
+
+    #[test]
+    fn finds_the_mermaid_block_in_markdown() {
+        let markdown = "# Food web\n\n```rust\nKR --> XX\n```\n\n```mermaid\nflowchart BT\n    KR --> PG\n```\n\nMore text\n";
+        let web = FoodWeb::from_markdown(markdown).unwrap();
+        assert_eq!(edges(&web), vec![("KR", "PG", EdgeKind::Feeding)]);
+        assert!(FoodWeb::from_markdown("no diagram here").is_err());
+    }
+
+    #[test]
+    fn reads_the_readme_diagram() {
+        let web = FoodWeb::load(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))).unwrap();
+        assert_eq!(web.nodes().len(), 15);
+        assert_eq!(web.node("PP").unwrap().name(), "Primary production");
+        assert!(web.eats("PG", "KR"));
+        assert!(web.eats("SE", "TF"));
+        assert!(!web.eats("KR", "PG"));
+        assert!(web.edges().contains(&Edge {
+            from: "PG".to_string(),
+            to: "BD".to_string(),
+            kind: EdgeKind::Detritus,
+        }));
+    }
+
+    #[test]
+    fn computes_trophic_levels() {
+        let web = web("PP --> KR --> PG --> SE\nPP --> ZP --> SF --> SE\nKR --> SE\nSE -.-> BD");
+        let levels = web.trophic_levels();
+        assert_eq!(levels["PP"], 1);
+        assert_eq!(levels["KR"], 2);
+        assert_eq!(levels["PG"], 3);
+        // The shortest chain to seals is through krill
+        assert_eq!(levels["SE"], 3);
+    }
+}
@@ -0,0 +1,222 @@
+//! Image optimisation for the bundle.
+//!
+//! The food-web PNGs are exported at print resolution and weigh ~150-200 KB
+//! each, far more than a 300px game card needs. [`ImageCache::optimize`]
+//! scales an image down to a maximum dimension, re-encodes it as a
+//! palette-quantised PNG (the fallback every browser can show) and optionally
+//! as a lossy WebP, and keeps the results on disk keyed by a hash of the
+//! source bytes and options so unchanged images are not re-encoded on every
+//! build.
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use color_quant::NeuQuant;
+use image::imageops::FilterType;
+use image::{ImageReader, RgbaImage};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// How to re-encode an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizeOptions {
+    /// Longest side in pixels. Larger images are scaled down, keeping their
+    /// aspect ratio; smaller ones keep their size.
+    pub max_dimension: u32,
+    /// Palette size (2-256) for the PNG, or `None` to keep full colour.
+    pub png_colors: Option<u16>,
+    /// Lossy WebP quality (0-100), or `None` to produce PNG only.
+    pub webp_quality: Option<f32>,
+}
+
+impl OptimizeOptions {
+    /// Part of the cache key, so changing an option re-encodes everything.
+    fn fingerprint(&self) -> String {
+        format!(
+            "max={};colors={:?};webp={:?};v1",
+            self.max_dimension, self.png_colors, self.webp_quality
+        )
+    }
+}
+
+/// The re-encoded variants of one image.
+#[derive(Debug, Clone)]
+pub struct OptimizedImage {
+    pub source_width: u32,
+    pub source_height: u32,
+    pub width: u32,
+    pub height: u32,
+    pub png: Vec<u8>,
+    pub webp: Option<Vec<u8>>,
+    /// Whether the variants came from the cache rather than being encoded now.
+    pub cached: bool,
+}
+
+/// A directory of previously optimised images.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+}
+
+impl ImageCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Optimises `source` (the bytes of a PNG, JPEG or WebP file), reusing
+    /// a cached result for the same bytes and options if there is one.
+    pub fn optimize(&self, source: &[u8], options: &OptimizeOptions) -> Result<OptimizedImage> {
+        let mut hasher = Sha256::new();
+        hasher.update(source);
+        hasher.update(options.fingerprint());
+        let key = format!("{:x}", hasher.finalize());
+        let png_path = self.dir.join(format!("{}.png", key));
+        let webp_path = self.dir.join(format!("{}.webp", key));
+
+        let (source_width, source_height) = dimensions(source)?;
+        if let Ok(png) = fs::read(&png_path) {
+            let webp = match options.webp_quality {
+                Some(_) => fs::read(&webp_path).ok(),
+                None => None,
+            };
+            if options.webp_quality.is_none() || webp.is_some() {
+                let (width, height) = dimensions(&png)?;
+                return Ok(OptimizedImage {
+                    source_width,
+                    source_height,
+                    width,
+                    height,
+                    png,
+                    webp,
+                    cached: true,
+                });
+            }
+        }
+
+        let optimized = optimize(source, options)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create image cache {}", self.dir.display()))?;
+        fs::write(&png_path, &optimized.png)
+            .with_context(|| format!("Failed to write {}", png_path.display()))?;
+        if let Some(webp) = &optimized.webp {
+            fs::write(&webp_path, webp)
+                .with_context(|| format!("Failed to write {}", webp_path.display()))?;
+        }
+        Ok(optimized)
+    }
+}
+
+/// Optimises `source` without touching the cache.
+pub fn optimize(source: &[u8], options: &OptimizeOptions) -> Result<OptimizedImage> {
+    let image = image::load_from_memory(source).context("Failed to decode image")?;
+    let (source_width, source_height) = (image.width(), image.height());
+
+    let max = options.max_dimension.max(1);
+    let image = if source_width > max || source_height > max {
+        image.resize(max, max, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    let rgba = image.to_rgba8();
+
+    let png = encode_png(&rgba, options.png_colors)?;
+    let webp = options
+        .webp_quality
+        .map(|quality| encode_webp(&rgba, quality))
+        .transpose()?;
+
+    Ok(OptimizedImage {
+        source_width,
+        source_height,
+        width: rgba.width(),
+        height: rgba.height(),
+        png,
+        webp,
+        cached: false,
+    })
+}
+
+/// Makes a square `size`×`size` PNG icon of `source`: the image is scaled
+/// to fit and centred on a transparent background.
+pub fn square_icon(source: &[u8], size: u32) -> Result<Vec<u8>> {
+    let image = image::load_from_memory(source).context("Failed to decode image")?;
+    let scaled = image.resize(size, size, FilterType::Lanczos3).to_rgba8();
+    let mut icon = RgbaImage::new(size, size);
+    let x = (size - scaled.width()) / 2;
+    let y = (size - scaled.height()) / 2;
+    image::imageops::overlay(&mut icon, &scaled, i64::from(x), i64::from(y));
+    encode_png(&icon, None)
+}
+
+/// A `data:` URI embedding `data`.
+pub fn data_uri(data: &[u8], mime: &str) -> String {
+    format!("data:{};base64,{}", mime, BASE64.encode(data))
+}
+
+/// The MIME type of an image file, from its extension.
+pub fn mime_type(name: &str) -> Result<&'static str> {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    Ok(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        _ => anyhow::bail!("Don't know how to inline image {} (unsupported file type)", name),
+    })
+}
+
+/// Reads the pixel size from an image header without decoding it.
+fn dimensions(bytes: &[u8]) -> Result<(u32, u32)> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()
+        .context("Failed to read image size")
+}
+
+/// Encodes a PNG, quantised to an indexed palette when `colors` is set.
+fn encode_png(rgba: &RgbaImage, colors: Option<u16>) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, rgba.width(), rgba.height());
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::High);
+
+    let data = match colors {
+        Some(colors) => {
+            let quantizer = NeuQuant::new(10, usize::from(colors.clamp(2, 256)), rgba.as_raw());
+            let palette = quantizer.color_map_rgba();
+            let rgb: Vec<u8> = palette.chunks(4).flat_map(|c| [c[0], c[1], c[2]]).collect();
+            let alpha: Vec<u8> = palette.chunks(4).map(|c| c[3]).collect();
+
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(rgb);
+            if alpha.iter().any(|&a| a < 255) {
+                encoder.set_trns(alpha);
+            }
+            rgba.pixels().map(|p| quantizer.index_of(&p.0) as u8).collect()
+        }
+        None => {
+            encoder.set_color(png::ColorType::Rgba);
+            rgba.as_raw().clone()
+        }
+    };
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(out)
+}
+
+fn encode_webp(rgba: &RgbaImage, quality: f32) -> Result<Vec<u8>> {
+    let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+        .encode_simple(false, quality.clamp(0.0, 100.0))
+        .map_err(|e| anyhow!("Failed to encode WebP: {:?}", e))?;
+    Ok(encoded.to_vec())
+}
@@ -0,0 +1,29 @@
+//! Shared library for the Just Learn Just build tools.
+//!
+//! Every binary in this crate (`validate`, `bundle`, `test-runner`,
+//! `render-all-questions`, ...) works on the same question-set files in
+//! `data/`. This library holds the one typed model of those files and the
+//! discovery logic that finds them, so the tools cannot drift apart on what a
+//! question set looks like.
+
+pub mod bundle;
+pub mod config;
+pub mod csp;
+pub mod factcheck;
+pub mod foodweb;
+pub mod images;
+pub mod minify;
+pub mod model;
+pub mod pwa;
+pub mod report;
+pub mod source_map;
+pub mod templates;
+pub mod test_report;
+pub mod validation;
+pub mod validator;
+pub mod watch;
+
+pub use model::{
+    discover_question_files, is_question_file, load_question_set, load_question_sets, Difficulty,
+    Metadata, Mode, Question, QuestionSet, QuestionSetFile,
+};
@@ -0,0 +1,323 @@
+//! In-process minification for `bundle --release`.
+//!
+//! The page embeds its stylesheet and scripts verbatim, comments and all.
+//! CSS goes through lightningcss. For JavaScript the available Rust
+//! minifiers either pull in a whole compiler or reject valid code (minify-js
+//! cannot parse the destructuring swap in `game-engine.js`), so
+//! [`minify_js`] is a small lexer that only removes comments and whitespace:
+//! it never renames or rewrites anything.
+
+use anyhow::{anyhow, Result};
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+
+/// Minifies a stylesheet: whitespace and comments go, colours and
+/// shorthands are shortened and duplicate rules merged.
+pub fn minify_css(source: &str) -> Result<String> {
+    let mut stylesheet = StyleSheet::parse(source, ParserOptions::default())
+        .map_err(|e| anyhow!("{}", e))?;
+    stylesheet
+        .minify(MinifyOptions::default())
+        .map_err(|e| anyhow!("{}", e))?;
+    let output = stylesheet
+        .to_css(PrinterOptions {
+            minify: true,
+            ..PrinterOptions::default()
+        })
+        .map_err(|e| anyhow!("{}", e))?;
+    Ok(output.code)
+}
+
+/// Keywords after which a `/` starts a regular expression, not a division.
+const REGEX_KEYWORDS: &[&str] = &[
+    "return", "typeof", "case", "do", "else", "in", "of", "new", "delete", "void", "throw",
+    "instanceof", "yield", "await",
+];
+
+/// Strips comments and collapses whitespace in a script.
+///
+/// A line break is kept wherever removing it could change how automatic
+/// semicolon insertion reads the code (`return\nx`, `a\n++b`, ...), and a
+/// space wherever two tokens would otherwise merge (`let x`, `a + +b`).
+/// Strings, template literals and regular expressions are copied verbatim.
+pub fn minify_js(source: &str) -> Result<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    // Whitespace seen since the last token: None, a space, or a line break
+    let mut pending: Option<char> = None;
+    // Open `${` in template literals, by the brace depth they were opened at
+    let mut templates: Vec<usize> = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // Whitespace and comments only record that a separator is needed
+        if c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}' {
+            pending = Some('\n');
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            pending.get_or_insert(' ');
+            i += 1;
+            continue;
+        }
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            let end = find(&chars, i + 2, "*/").ok_or_else(|| anyhow!("Unterminated comment"))?;
+            if chars[i..end].contains(&'\n') {
+                pending = Some('\n');
+            } else {
+                pending.get_or_insert(' ');
+            }
+            i = end + 2;
+            continue;
+        }
+
+        if let Some(separator) = pending.take() {
+            if let Some(prev) = out.chars().last() {
+                if separator == '\n' && !can_join_lines(prev, c) {
+                    out.push('\n');
+                } else if would_merge(prev, c) {
+                    out.push(' ');
+                }
+            }
+        }
+
+        match c {
+            '\'' | '"' => {
+                let end = string_end(&chars, i, c)?;
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            '`' => i = template_chunk(&chars, i + 1, &mut out, &mut templates, depth)?,
+            '}' if templates.last() == Some(&depth) => {
+                // End of a `${...}` substitution: back inside the template
+                templates.pop();
+                i = template_chunk(&chars, i + 1, &mut out, &mut templates, depth)?;
+            }
+            '/' if regex_allowed(&out) => {
+                let end = regex_end(&chars, i)?;
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            _ => {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Whether a line break between `prev` and `next` can be dropped without
+/// changing semicolon insertion: the previous line obviously continues, or
+/// the next one obviously continues the previous.
+fn can_join_lines(prev: char, next: char) -> bool {
+    "{([,;:=&|?!~<>*%^".contains(prev) || "})],;.:?=&|".contains(next)
+}
+
+/// Whether `prev` and `next` would lex as one token without a space.
+fn would_merge(prev: char, next: char) -> bool {
+    let word = |c: char| c.is_alphanumeric() || c == '_' || c == '$' || !c.is_ascii();
+    (word(prev) && word(next))
+        || (prev == '+' && next == '+')
+        || (prev == '-' && next == '-')
+        || (prev == '/' && next == '/')
+        || (prev == '.' && next.is_ascii_digit())
+        || (prev.is_ascii_digit() && next == '.')
+}
+
+/// Whether a `/` after the output so far starts a regular expression.
+fn regex_allowed(out: &str) -> bool {
+    let trimmed = out.trim_end();
+    let Some(prev) = trimmed.chars().last() else {
+        return true;
+    };
+    if prev.is_alphanumeric() || prev == '_' || prev == '$' {
+        let word: String = trimmed
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        return REGEX_KEYWORDS.contains(&word.as_str());
+    }
+    !matches!(prev, ')' | ']' | '\'' | '"' | '`')
+}
+
+fn find(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    let needle: Vec<char> = needle.chars().collect();
+    (from..chars.len()).find(|&i| chars[i..].starts_with(&needle))
+}
+
+/// The index just past the string literal starting at `start`.
+fn string_end(chars: &[char], start: usize, quote: char) -> Result<usize> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => return Ok(i + 1),
+            '\n' => break,
+            _ => i += 1,
+        }
+    }
+    Err(anyhow!("Unterminated string literal"))
+}
+
+/// The index just past the regular expression literal (and flags) at `start`.
+fn regex_end(chars: &[char], start: usize) -> Result<usize> {
+    let mut i = start + 1;
+    let mut in_class = false;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '[' => {
+                in_class = true;
+                i += 1;
+            }
+            ']' => {
+                in_class = false;
+                i += 1;
+            }
+            '/' if !in_class => {
+                i += 1;
+                while i < chars.len() && chars[i].is_alphanumeric() {
+                    i += 1;
+                }
+                return Ok(i);
+            }
+            '\n' => break,
+            _ => i += 1,
+        }
+    }
+    Err(anyhow!("Unterminated regular expression"))
+}
+
+/// Copies template literal text from `start` (just after a backtick or the
+/// `}` closing a substitution) up to and including the closing backtick or
+/// the next `${`. Returns the index to continue from.
+fn template_chunk(
+    chars: &[char],
+    start: usize,
+    out: &mut String,
+    templates: &mut Vec<usize>,
+    depth: usize,
+) -> Result<usize> {
+    out.push(chars[start - 1]);
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                out.extend(chars.get(i..i + 2).unwrap_or(&chars[i..]));
+                i += 2;
+            }
+            '`' => {
+                out.push('`');
+                return Ok(i + 1);
+            }
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                out.push_str("${");
+                templates.push(depth);
+                return Ok(i + 2);
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(anyhow!("Unterminated template literal"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn js(source: &str) -> String {
+        minify_js(source).unwrap()
+    }
+
+    #[test]
+    fn keeps_line_breaks_that_semicolon_insertion_needs() {
+        assert_eq!(js("function f() {\n    return\n    x;\n}"), "function f(){return\nx;}");
+        assert_eq!(js("a\n++b"), "a\n++b");
+        assert_eq!(js("let a = 1\nlet b = 2"), "let a=1\nlet b=2");
+        assert_eq!(js("f()\n(g)()"), "f()\n(g)()");
+        assert_eq!(js("x = y\n[1, 2].forEach(f)"), "x=y\n[1,2].forEach(f)");
+    }
+
+    #[test]
+    fn joins_lines_that_obviously_continue() {
+        assert_eq!(js("call(\n    a,\n    b\n)"), "call(a,b)");
+        assert_eq!(js("promise\n    .then(f)\n    .catch(g);"), "promise.then(f).catch(g);");
+        assert_eq!(js("const x = cond\n    ? 1\n    : 2;"), "const x=cond?1:2;");
+        assert_eq!(js("if (a) {\n    b();\n}"), "if(a){b();}");
+        // `}` may close an object literal, so the next line is kept apart
+        assert_eq!(js("a = {}\n(b)"), "a={}\n(b)");
+    }
+
+    #[test]
+    fn keeps_spaces_between_tokens_that_would_merge() {
+        assert_eq!(js("let   x = typeof y"), "let x=typeof y");
+        assert_eq!(js("a + +b - -c"), "a+ +b- -c");
+        assert_eq!(js("1 .toString()"), "1 .toString()");
+    }
+
+    #[test]
+    fn tells_regular_expressions_from_division() {
+        // After `)`, `]` and identifiers a slash divides
+        assert_eq!(js("(a + b) / 2 / c"), "(a+b)/2/c");
+        assert_eq!(js("x[i] / 2"), "x[i]/2");
+        assert_eq!(js("total / count // ratio"), "total/count");
+        // Elsewhere it starts a regular expression, copied as is
+        assert_eq!(js("const re = / a  b /g;"), "const re=/ a  b /g;");
+        assert_eq!(js("return /[/ ]+/.test(s)"), "return/[/ ]+/.test(s)");
+        assert_eq!(js("f(/\\/ x/, 1)"), "f(/\\/ x/,1)");
+        assert_eq!(js("a = b ? /x y/ : /z  w/"), "a=b?/x y/:/z  w/");
+    }
+
+    #[test]
+    fn copies_template_literals_and_minifies_their_substitutions() {
+        assert_eq!(js("`a  ${ x + 1 }  b`"), "`a  ${x+1}  b`");
+        assert_eq!(js("`outer ${ `inner ${ y }` }`"), "`outer ${`inner ${y}`}`");
+        assert_eq!(js("`${ { a: 1 }.a } // not a comment`"), "`${{a:1}.a} // not a comment`");
+        assert_eq!(js("`line one\n  line two`"), "`line one\n  line two`");
+        assert_eq!(js("`\\${ not } a substitution`"), "`\\${ not } a substitution`");
+    }
+
+    #[test]
+    fn leaves_comment_markers_inside_strings_alone() {
+        assert_eq!(js("const url = 'https://example.org'; // site"), "const url='https://example.org';");
+        assert_eq!(js("s = \"/* kept */\" /* dropped */ + t"), "s=\"/* kept */\"+t");
+        assert_eq!(js("s = 'it\\'s // still a string'"), "s='it\\'s // still a string'");
+    }
+
+    #[test]
+    fn comments_separate_tokens() {
+        assert_eq!(js("let/* c */x"), "let x");
+        assert_eq!(js("a = 1 /* one\n two */ b = 2"), "a=1\nb=2");
+    }
+
+    #[test]
+    fn reports_unterminated_literals() {
+        assert!(minify_js("'abc").is_err());
+        assert!(minify_js("`abc ${ x }").is_err());
+        assert!(minify_js("x = /abc").is_err());
+        assert!(minify_js("/* abc").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Typed model of the `data/questions_*.json` files.
+//!
+//! The field names and optionality mirror `data/schema.json`. Files are
+//! still validated against the schema by `validate`; these types are what
+//! the rest of the tooling reads once a file is known to be well formed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A complete question set: one `questions_*.json` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuestionSet {
+    /// Title, audience and game mode for the set.
+    pub metadata: Metadata,
+    /// The questions, in file order. The game shuffles them at runtime.
+    pub questions: Vec<Question>,
+}
+
+/// Descriptive information shown on the start screen tile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Tile title, e.g. "Ross Sea Food Web - Little Explorers".
+    pub title: String,
+    /// Longer text shown below the tiles on hover.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Content version of the set (free-form, usually semver).
+    pub version: String,
+    /// Intended audience, e.g. "5-8 years".
+    #[serde(rename = "targetAge")]
+    pub target_age: String,
+    /// Subject area, e.g. "Marine Biology".
+    pub subject: String,
+    /// How the game treats wrong answers for this set.
+    pub mode: Mode,
+}
+
+/// Game mode of a question set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Wrong answers are marked and the player may try again.
+    Easy,
+    /// The first answer is final and the result screen is shown immediately.
+    Hard,
+}
+
+impl Mode {
+    /// The spelling used in the JSON files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Easy => "easy",
+            Mode::Hard => "hard",
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Per-question difficulty label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// The spelling used in the JSON files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single multiple-choice question.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    /// Identifier, unique across all question sets (e.g. `kids_1`).
+    pub id: String,
+    /// The question text.
+    pub question: String,
+    /// Image shown with the question, relative to the page (e.g. `KR.png`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image1: Option<String>,
+    /// Image shown on the answer screen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image2: Option<String>,
+    /// Answer choices, in display order.
+    pub choices: Vec<String>,
+    /// Zero-based index into `choices` of the correct answer.
+    #[serde(rename = "correctAnswer")]
+    pub correct_answer: usize,
+    /// Text shown on the result screen.
+    pub explanation: String,
+    /// Topic grouping, e.g. `food-chain`.
+    pub category: String,
+    /// Difficulty label for this question.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    /// Free-form tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Question {
+    /// The text of the correct choice, if `correct_answer` is in range.
+    pub fn correct_choice(&self) -> Option<&str> {
+        self.choices.get(self.correct_answer).map(String::as_str)
+    }
+
+    /// The images referenced by this question, in `image1`, `image2` order.
+    pub fn images(&self) -> impl Iterator<Item = &str> {
+        self.image1.iter().chain(self.image2.iter()).map(String::as_str)
+    }
+}
+
+/// A question set together with where it was loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionSetFile {
+    /// File stem, used as the set's key in the bundle (e.g. `questions_ross_sea_easy`).
+    pub key: String,
+    /// Path of the JSON file.
+    pub path: PathBuf,
+    /// The parsed contents.
+    pub set: QuestionSet,
+}
+
+impl QuestionSetFile {
+    /// File name of the JSON file, for display.
+    pub fn filename(&self) -> &str {
+        self.path.file_name().and_then(|n| n.to_str()).unwrap_or(&self.key)
+    }
+}
+
+/// Whether `path` names a question-set file (`questions*.json`).
+pub fn is_question_file(path: &Path) -> bool {
+    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    filename.starts_with("questions") && filename.ends_with(".json")
+}
+
+/// Lists the question-set files directly inside `dir`, sorted by file name.
+pub fn discover_question_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        anyhow::bail!("Data directory not found at: {}", dir.display());
+    }
+
+    let mut paths: Vec<PathBuf> = WalkDir::new(dir)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_question_file(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Reads and parses a single question-set file.
+pub fn load_question_set(path: &Path) -> Result<QuestionSet> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Discovers and loads every question set in `dir`, sorted by file name.
+///
+/// Fails on the first file that cannot be read or parsed. Use
+/// [`discover_question_files`] when per-file error reporting is needed.
+pub fn load_question_sets(dir: &Path) -> Result<Vec<QuestionSetFile>> {
+    discover_question_files(dir)?
+        .into_iter()
+        .map(|path| {
+            let set = load_question_set(&path)?;
+            let key = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+            Ok(QuestionSetFile { key, path, set })
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+//! Progressive Web App output for `bundle --pwa`.
+//!
+//! Classroom tablets often lose their connection mid-lesson. With `--pwa` the
+//! bundle also writes, next to the page, a web app manifest, square icons
+//! made from one of the question PNGs, and a service worker (`sw.js`, from
+//! `paths.service-worker`) that precaches the page and every image it shows.
+//! The worker's cache is named after the build's content hash, so deploying
+//! a different build replaces the cache and an identical rebuild keeps it.
+
+use crate::images::square_icon;
+use crate::templates::script_json;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// File names, relative to the page.
+pub const MANIFEST: &str = "manifest.webmanifest";
+pub const SERVICE_WORKER: &str = "sw.js";
+
+/// Icon sizes the manifest lists: the minimum browsers require to offer
+/// installing the app.
+pub const ICON_SIZES: [u32; 2] = [192, 512];
+
+/// Shared by the caches of every build, so a new worker can tell which old
+/// caches are its own to delete.
+const CACHE_PREFIX: &str = "just-learn-";
+
+/// What the manifest says about the app.
+#[derive(Debug, Clone)]
+pub struct App {
+    pub name: String,
+    pub short_name: String,
+    pub description: String,
+    pub lang: String,
+    /// The page, relative to the manifest.
+    pub start_url: String,
+    pub theme_color: String,
+    pub background_color: String,
+}
+
+/// Where the icon of `size` pixels is written, relative to the page.
+pub fn icon_path(size: u32) -> String {
+    format!("icons/icon-{}.png", size)
+}
+
+/// Writes the icons for every size in [`ICON_SIZES`] from the image bytes
+/// `source` into `page_dir`, returning their paths relative to the page.
+pub fn write_icons(source: &[u8], page_dir: &Path) -> Result<Vec<String>> {
+    ICON_SIZES
+        .iter()
+        .map(|&size| {
+            let name = icon_path(size);
+            let path = page_dir.join(&name);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+            let icon = square_icon(source, size).context("Failed to make the app icon")?;
+            fs::write(&path, icon).with_context(|| format!("Failed to write {}", path.display()))?;
+            Ok(name)
+        })
+        .collect()
+}
+
+/// The web app manifest.
+pub fn manifest(app: &App) -> Value {
+    let icons: Vec<Value> = ICON_SIZES
+        .iter()
+        .map(|&size| {
+            json!({
+                "src": icon_path(size),
+                "sizes": format!("{}x{}", size, size),
+                "type": "image/png",
+                "purpose": "any",
+            })
+        })
+        .collect();
+    json!({
+        "name": app.name,
+        "short_name": app.short_name,
+        "description": app.description,
+        "lang": app.lang,
+        "start_url": app.start_url,
+        "scope": "./",
+        "display": "standalone",
+        "orientation": "any",
+        "theme_color": app.theme_color,
+        "background_color": app.background_color,
+        "icons": icons,
+    })
+}
+
+/// The service worker: `script` preceded by the constants it reads. The
+/// cache is named after `content_hash`; `precache` lists the URLs, relative
+/// to the worker, fetched on install.
+pub fn service_worker(script: &str, content_hash: &str, start_url: &str, precache: &[String]) -> String {
+    let cache_name = format!("{}{}", CACHE_PREFIX, &content_hash[..content_hash.len().min(16)]);
+    let constants = [
+        ("CACHE_PREFIX", json!(CACHE_PREFIX)),
+        ("CACHE_NAME", json!(cache_name)),
+        ("START_URL", json!(start_url)),
+        ("PRECACHE_URLS", json!(precache)),
+    ];
+    let mut worker = "// Generated by bundle --pwa\n".to_string();
+    for (name, value) in constants {
+        worker += &format!("const {} = {};\n", name, script_json(&value.to_string()));
+    }
+    worker + "\n" + script
+}
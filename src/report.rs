@@ -0,0 +1,344 @@
+//! Machine-readable renderings of validation results.
+//!
+//! `validate --format json|junit|sarif` hands these to CI dashboards and
+//! editors so failures show up as annotations instead of being scraped from
+//! the colored text output.
+
+use crate::validation::{Diagnostic, Severity, RULES};
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// All findings for one validated file.
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl FileReport {
+    /// A file is valid when it has no error-level findings.
+    pub fn is_valid(&self) -> bool {
+        !self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == severity).count()
+    }
+}
+
+/// Plain JSON: every file with its diagnostics, plus totals.
+pub fn to_json(files: &[FileReport]) -> Value {
+    let files_json: Vec<Value> = files
+        .iter()
+        .map(|f| {
+            json!({
+                "file": f.path,
+                "valid": f.is_valid(),
+                "diagnostics": f.diagnostics,
+            })
+        })
+        .collect();
+
+    json!({
+        "files": files_json,
+        "summary": {
+            "files": files.len(),
+            "invalid": files.iter().filter(|f| !f.is_valid()).count(),
+            "errors": files.iter().map(|f| f.count(Severity::Error)).sum::<usize>(),
+            "warnings": files.iter().map(|f| f.count(Severity::Warning)).sum::<usize>(),
+        }
+    })
+}
+
+/// JUnit XML: one test case per file. Errors become a single `<failure>`
+/// listing every finding; warnings go to `<system-out>`.
+pub fn to_junit(files: &[FileReport]) -> String {
+    let failures = files.iter().filter(|f| !f.is_valid()).count();
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"validate\" tests=\"{}\" failures=\"{}\">",
+        files.len(),
+        failures
+    );
+    let _ = writeln!(
+        out,
+        "  <testsuite name=\"validate\" tests=\"{}\" failures=\"{}\" errors=\"0\">",
+        files.len(),
+        failures
+    );
+
+    for file in files {
+        let name = xml_escape(&file.path.display().to_string());
+        let errors: Vec<&Diagnostic> = file.diagnostics.iter().filter(|d| d.is_error()).collect();
+        let warnings: Vec<&Diagnostic> = file.diagnostics.iter().filter(|d| !d.is_error()).collect();
+
+        if errors.is_empty() && warnings.is_empty() {
+            let _ = writeln!(out, "    <testcase classname=\"validate\" name=\"{}\"/>", name);
+            continue;
+        }
+
+        let _ = writeln!(out, "    <testcase classname=\"validate\" name=\"{}\">", name);
+        if !errors.is_empty() {
+            let mut rules: Vec<&str> = errors.iter().map(|d| d.rule).collect();
+            rules.sort_unstable();
+            rules.dedup();
+            let _ = writeln!(
+                out,
+                "      <failure message=\"{} error(s)\" type=\"{}\">{}</failure>",
+                errors.len(),
+                xml_escape(&rules.join(",")),
+                xml_escape(&errors.iter().map(|d| describe(d)).collect::<Vec<_>>().join("\n"))
+            );
+        }
+        if !warnings.is_empty() {
+            let _ = writeln!(
+                out,
+                "      <system-out>{}</system-out>",
+                xml_escape(&warnings.iter().map(|d| describe(d)).collect::<Vec<_>>().join("\n"))
+            );
+        }
+        out.push_str("    </testcase>\n");
+    }
+
+    out.push_str("  </testsuite>\n");
+    out.push_str("</testsuites>\n");
+    out
+}
+
+/// SARIF 2.1.0, as understood by GitHub code scanning and most editors.
+pub fn to_sarif(files: &[FileReport]) -> Value {
+    let cwd = std::env::current_dir().ok();
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|(id, description)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": description },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = files
+        .iter()
+        .flat_map(|f| f.diagnostics.iter())
+        .map(|d| {
+            let mut physical = json!({
+                "artifactLocation": { "uri": sarif_uri(&d.file, cwd.as_deref()) },
+            });
+            if let Some(location) = &d.location {
+                physical["region"] = json!({
+                    "startLine": location.line,
+                    "startColumn": location.column.max(1),
+                });
+            }
+            let pointer = if d.pointer.is_empty() { "/" } else { d.pointer.as_str() };
+            json!({
+                "ruleId": d.rule,
+                "level": d.severity.as_str(),
+                "message": { "text": d.message },
+                "locations": [{
+                    "physicalLocation": physical,
+                    "logicalLocations": [{ "fullyQualifiedName": pointer, "kind": "member" }],
+                }],
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "validate",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            // Location columns count characters, not bytes or UTF-16 units
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }]
+    })
+}
+
+/// `file:line:col [rule] /pointer: message`
+fn describe(d: &Diagnostic) -> String {
+    let position = match &d.location {
+        Some(l) => format!("{}:{}:{}", d.file.display(), l.line, l.column),
+        None => d.file.display().to_string(),
+    };
+    format!("{} [{}] {}: {}", position, d.rule, d.pointer, d.message)
+}
+
+/// SARIF wants URIs. Files under the working directory, where CI checks the
+/// repository out, are given relative to it so annotations land on the
+/// right file; any other absolute path becomes a `file://` URI.
+fn sarif_uri(file: &Path, cwd: Option<&Path>) -> String {
+    let relative = match cwd {
+        Some(cwd) if file.is_absolute() => file.strip_prefix(cwd).unwrap_or(file),
+        _ => file,
+    };
+    let path = relative
+        .to_string_lossy()
+        .replace('\\', "/")
+        .replace('%', "%25")
+        .replace(' ', "%20");
+    if !relative.is_absolute() {
+        path
+    } else if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        // A Windows path such as C:/data
+        format!("file:///{}", path)
+    }
+}
+
+/// Escapes text for use in XML attributes and element content.
+pub fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source_map::Location;
+    use crate::validation::{RULE_CORRECT_ANSWER_RANGE, RULE_EMPTY_EXPLANATION, RULE_SCHEMA};
+
+    fn diagnostic(file: &str, rule: &'static str, severity: Severity, message: &str) -> Diagnostic {
+        let mut d = Diagnostic::new(Path::new(file), Some("q1"), "/questions/0", rule, severity, message.to_string());
+        d.location = Some(Location {
+            line: 12,
+            column: 24,
+            offset: 0,
+            len: 1,
+        });
+        d
+    }
+
+    fn reports() -> Vec<FileReport> {
+        vec![
+            FileReport {
+                path: PathBuf::from("data/questions_a.json"),
+                diagnostics: Vec::new(),
+            },
+            FileReport {
+                path: PathBuf::from("data/questions_<b>.json"),
+                diagnostics: vec![
+                    diagnostic(
+                        "data/questions_<b>.json",
+                        RULE_CORRECT_ANSWER_RANGE,
+                        Severity::Error,
+                        "correctAnswer is 7 but there are only 2 choices",
+                    ),
+                    diagnostic("data/questions_<b>.json", RULE_SCHEMA, Severity::Error, "\"id\" is required"),
+                    diagnostic(
+                        "data/questions_<b>.json",
+                        RULE_EMPTY_EXPLANATION,
+                        Severity::Warning,
+                        "Explanation is empty & unhelpful",
+                    ),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn junit_has_a_test_case_per_file() {
+        let xml = to_junit(&reports());
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"validate\" tests=\"2\" failures=\"1\">"));
+        assert!(xml.contains("<testcase classname=\"validate\" name=\"data/questions_a.json\"/>"));
+        assert!(xml.contains(
+            "<failure message=\"2 error(s)\" type=\"correct-answer-range,schema\">data/questions_&lt;b&gt;.json:12:24 [correct-answer-range] /questions/0: correctAnswer is 7 but there are only 2 choices\n\
+             data/questions_&lt;b&gt;.json:12:24 [schema] /questions/0: &quot;id&quot; is required</failure>"
+        ));
+        assert!(xml.contains("<system-out>data/questions_&lt;b&gt;.json:12:24 [empty-explanation] /questions/0: Explanation is empty &amp; unhelpful</system-out>"));
+        assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+
+    #[test]
+    fn junit_escapes_file_names() {
+        let xml = to_junit(&reports());
+        assert!(xml.contains("<testcase classname=\"validate\" name=\"data/questions_&lt;b&gt;.json\">"));
+        assert!(!xml.contains("<b>"));
+    }
+
+    #[test]
+    fn escapes_xml_special_characters() {
+        assert_eq!(xml_escape("a < b && c > \"d\" 'e'"), "a &lt; b &amp;&amp; c &gt; &quot;d&quot; &apos;e&apos;");
+    }
+
+    #[test]
+    fn sarif_lists_every_finding_with_its_region() {
+        let sarif = to_sarif(&reports());
+        assert_eq!(sarif["version"], "2.1.0");
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0],
+            json!({
+                "ruleId": "correct-answer-range",
+                "level": "error",
+                "message": { "text": "correctAnswer is 7 but there are only 2 choices" },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "data/questions_<b>.json" },
+                        "region": { "startLine": 12, "startColumn": 24 },
+                    },
+                    "logicalLocations": [{ "fullyQualifiedName": "/questions/0", "kind": "member" }],
+                }],
+            })
+        );
+        assert_eq!(results[2]["level"], "warning");
+        let rules = sarif["runs"][0]["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), RULES.len());
+    }
+
+    #[test]
+    fn sarif_uris_are_relative_to_the_working_directory() {
+        let cwd = Path::new("/home/ci/repo");
+        assert_eq!(sarif_uri(Path::new("data/questions_a.json"), Some(cwd)), "data/questions_a.json");
+        assert_eq!(
+            sarif_uri(Path::new("/home/ci/repo/data/questions_a.json"), Some(cwd)),
+            "data/questions_a.json"
+        );
+        assert_eq!(
+            sarif_uri(Path::new("/tmp/my data/questions_a.json"), Some(cwd)),
+            "file:///tmp/my%20data/questions_a.json"
+        );
+        assert_eq!(sarif_uri(Path::new("data/100%.json"), None), "data/100%25.json");
+    }
+
+    #[test]
+    fn sarif_columns_count_code_points() {
+        let source = "{\"title\": \"🐧\", \"x\": 7}";
+        let mut d = diagnostic("data/questions_a.json", RULE_SCHEMA, Severity::Error, "bad");
+        d.location = crate::source_map::SourceMap::parse(source).unwrap().locate("/x");
+        let files = [FileReport {
+            path: PathBuf::from("data/questions_a.json"),
+            diagnostics: vec![d],
+        }];
+        let sarif = to_sarif(&files);
+        assert_eq!(sarif["runs"][0]["columnKind"], "unicodeCodePoints");
+        // 24 in bytes and 22 in UTF-16 code units
+        assert_eq!(
+            sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"],
+            json!({ "startLine": 1, "startColumn": 21 })
+        );
+    }
+}
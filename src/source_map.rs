@@ -0,0 +1,375 @@
+//! Maps JSON pointers back to positions in the original file text.
+//!
+//! `serde_json` and `jsonschema` only know where a value sits in the parsed
+//! tree (`/questions/3/correctAnswer`). To point authors at the right spot
+//! in a 15-question file we re-scan the source text, record the byte span of
+//! every value under its pointer, and render rustc-style code frames.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// A position in a source file. Lines and columns are 1-based; columns count
+/// characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    /// Byte offset of the start of the value.
+    pub offset: usize,
+    /// Length of the value in bytes.
+    pub len: usize,
+}
+
+/// Byte spans of every value in a JSON document, keyed by JSON pointer.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    spans: HashMap<String, (usize, usize)>,
+    line_starts: Vec<usize>,
+    source: String,
+}
+
+impl SourceMap {
+    /// Scans `source` and records the span of each value. Returns `None` if
+    /// the text is not well-formed JSON.
+    pub fn parse(source: &str) -> Option<Self> {
+        let mut scanner = Scanner {
+            bytes: source.as_bytes(),
+            pos: 0,
+            spans: HashMap::new(),
+        };
+        scanner.skip_ws();
+        scanner.value(String::new())?;
+        scanner.skip_ws();
+        if scanner.pos != source.len() {
+            return None;
+        }
+
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Some(Self {
+            spans: scanner.spans,
+            line_starts,
+            source: source.to_string(),
+        })
+    }
+
+    /// The location of the value at `pointer`. If the pointer does not exist
+    /// (e.g. a missing required property) the nearest existing ancestor is
+    /// used instead.
+    pub fn locate(&self, pointer: &str) -> Option<Location> {
+        let mut pointer = pointer;
+        loop {
+            if let Some(&(start, end)) = self.spans.get(pointer) {
+                let (line, column) = self.line_column(start);
+                return Some(Location {
+                    line,
+                    column,
+                    offset: start,
+                    len: end - start,
+                });
+            }
+            if pointer.is_empty() {
+                return None;
+            }
+            pointer = &pointer[..pointer.rfind('/').unwrap_or(0)];
+        }
+    }
+
+    /// Converts a 1-based line and column as reported by `serde_json`, which
+    /// counts the column in bytes, into a zero-length [`Location`] whose
+    /// column counts characters like the others.
+    pub fn location_at(source: &str, line: usize, column: usize) -> Location {
+        let line_start: usize = source
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>()
+            .min(source.len());
+        let text = source[line_start..].split('\n').next().unwrap_or("");
+        let mut byte = column.saturating_sub(1).min(text.len());
+        while !text.is_char_boundary(byte) {
+            byte -= 1;
+        }
+        Location {
+            line,
+            column: text[..byte].chars().count() + 1,
+            offset: line_start + byte,
+            len: 0,
+        }
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line_idx = match self.line_starts.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let column = self.source[self.line_starts[line_idx]..offset].chars().count() + 1;
+        (line_idx + 1, column)
+    }
+}
+
+/// Renders a rustc-style excerpt of `source` around `location`, with one line
+/// of context either side and the value underlined, followed by `label`.
+/// `validate` prints the message above the frame and labels the value with
+/// the question it belongs to, if any:
+///
+/// ```text
+///   --> data/questions_ross_sea_easy.json:12:24
+///    |
+/// 11 |       "choices": ["Penguins", "Bacteria"],
+/// 12 |       "correctAnswer": 7,
+///    |                        ^ in question 'easy_3'
+/// 13 |       "explanation": "Penguins love to eat krill!",
+///    |
+/// ```
+///
+/// Values spanning several lines are marked on their first line only.
+pub fn code_frame(source: &str, file: &str, location: &Location, label: &str) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let first = location.line.saturating_sub(1).max(1);
+    let last = (location.line + 1).min(lines.len());
+    let width = last.to_string().len();
+    let gutter = " ".repeat(width);
+    let indent = location.column.saturating_sub(1);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}--> {}:{}:{}", gutter, file, location.line, location.column);
+    let _ = writeln!(out, "{} |", gutter);
+    for number in first..=last {
+        let text = lines.get(number - 1).copied().unwrap_or("");
+        let _ = writeln!(out, "{:>width$} | {}", number, text, width = width);
+        if number == location.line {
+            let line_rest: String = text.chars().skip(indent).collect();
+            let value = source
+                .get(location.offset..location.offset + location.len)
+                .unwrap_or("");
+            let underline = value
+                .lines()
+                .next()
+                .map(|l| l.chars().count())
+                .unwrap_or(0)
+                .clamp(1, line_rest.chars().count().max(1));
+            let marker = format!("{}{} {}", " ".repeat(indent), "^".repeat(underline), label);
+            let _ = writeln!(out, "{} | {}", gutter, marker.trim_end());
+        }
+    }
+    let _ = write!(out, "{} |", gutter);
+    out
+}
+
+/// Escapes a property name for use as a JSON pointer segment (RFC 6901).
+pub fn escape_pointer_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    spans: HashMap<String, (usize, usize)>,
+}
+
+impl Scanner<'_> {
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> Option<()> {
+        self.skip_ws();
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self, pointer: String) -> Option<()> {
+        self.skip_ws();
+        let start = self.pos;
+        match *self.bytes.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                if self.eat(b'}').is_none() {
+                    loop {
+                        self.skip_ws();
+                        let key_start = self.pos;
+                        self.string()?;
+                        let raw_key = std::str::from_utf8(&self.bytes[key_start..self.pos]).ok()?;
+                        let key: String = serde_json::from_str(raw_key).ok()?;
+                        self.eat(b':')?;
+                        self.value(format!("{}/{}", pointer, escape_pointer_segment(&key)))?;
+                        if self.eat(b',').is_none() {
+                            self.eat(b'}')?;
+                            break;
+                        }
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                if self.eat(b']').is_none() {
+                    let mut index = 0;
+                    loop {
+                        self.value(format!("{}/{}", pointer, index))?;
+                        index += 1;
+                        if self.eat(b',').is_none() {
+                            self.eat(b']')?;
+                            break;
+                        }
+                    }
+                }
+            }
+            b'"' => self.string()?,
+            _ => {
+                while let Some(b) = self.bytes.get(self.pos) {
+                    if matches!(b, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return None;
+                }
+            }
+        }
+        self.spans.insert(pointer, (start, self.pos));
+        Some(())
+    }
+
+    fn string(&mut self) -> Option<()> {
+        if self.bytes.get(self.pos) != Some(&b'"') {
+            return None;
+        }
+        self.pos += 1;
+        loop {
+            match *self.bytes.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{
+  "metadata": {"title": "Ross Sea"},
+  "a/b": 1,
+  "c~d": [true, null],
+  "questions": [
+    {"id": "q1", "choices": ["Krill", "Phytoplankton"]},
+    {"id": "q2", "question": "Wo lebt der Kaiserpinguin? 🐧", "correctAnswer": 7}
+  ]
+}"#;
+
+    fn locate(pointer: &str) -> (usize, usize, &'static str) {
+        let map = SourceMap::parse(SOURCE).unwrap();
+        let location = map.locate(pointer).unwrap();
+        (location.line, location.column, &SOURCE[location.offset..location.offset + location.len])
+    }
+
+    #[test]
+    fn locates_object_members() {
+        assert_eq!(locate("/metadata/title"), (2, 25, "\"Ross Sea\""));
+        assert_eq!(locate("/metadata"), (2, 15, "{\"title\": \"Ross Sea\"}"));
+        assert_eq!(locate(""), (1, 1, SOURCE));
+    }
+
+    #[test]
+    fn locates_keys_with_escaped_pointer_characters() {
+        assert_eq!(escape_pointer_segment("a/b"), "a~1b");
+        assert_eq!(escape_pointer_segment("c~d"), "c~0d");
+        assert_eq!(locate("/a~1b"), (3, 10, "1"));
+        assert_eq!(locate("/c~0d/1"), (4, 17, "null"));
+    }
+
+    #[test]
+    fn locates_array_elements() {
+        assert_eq!(locate("/c~0d/0"), (4, 11, "true"));
+        assert_eq!(locate("/questions/0/id"), (6, 12, "\"q1\""));
+        assert_eq!(locate("/questions/0/choices/1"), (6, 39, "\"Phytoplankton\""));
+        assert_eq!(locate("/questions/1/id"), (7, 12, "\"q2\""));
+    }
+
+    #[test]
+    fn counts_columns_in_characters_after_multibyte_text() {
+        // The emoji is four bytes but one column
+        assert_eq!(locate("/questions/1/correctAnswer"), (7, 79, "7"));
+    }
+
+    #[test]
+    fn falls_back_to_the_nearest_existing_ancestor() {
+        assert_eq!(locate("/questions/1/explanation"), locate("/questions/1"));
+        assert_eq!(locate("/questions/9/id"), locate("/questions"));
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        assert!(SourceMap::parse("{\"a\": 1,}").is_none());
+        assert!(SourceMap::parse("[1, 2").is_none());
+        assert!(SourceMap::parse("{} trailing").is_none());
+    }
+
+    /// Where `serde_json` says `source` is malformed.
+    fn syntax_error_at(source: &str) -> Location {
+        let e = serde_json::from_str::<serde_json::Value>(source).unwrap_err();
+        SourceMap::location_at(source, e.line(), e.column())
+    }
+
+    #[test]
+    fn converts_serde_json_positions_to_offsets() {
+        let source = "{\n  \"ü\": x\n}";
+        let location = syntax_error_at(source);
+        assert_eq!((location.line, location.column), (2, 8));
+        assert_eq!(&source[location.offset..], "x\n}");
+    }
+
+    #[test]
+    fn counts_serde_json_columns_as_bytes() {
+        // serde_json reports column 19 here: three two-byte characters
+        // before the error
+        let source = r#"{"a": "ü ü ü", x}"#;
+        assert_eq!(serde_json::from_str::<serde_json::Value>(source).unwrap_err().column(), 19);
+        let location = syntax_error_at(source);
+        assert_eq!((location.line, location.column), (1, 16));
+        assert_eq!(&source[location.offset..], "x}");
+        let frame = code_frame(source, "q.json", &location, "");
+        assert!(frame.contains(&format!("  | {}^", " ".repeat(15))));
+    }
+
+    #[test]
+    fn keeps_positions_past_the_end_inside_the_source() {
+        let location = SourceMap::location_at("[1,\n", 5, 40);
+        assert_eq!(location.offset, 4);
+    }
+
+    #[test]
+    fn renders_a_code_frame_with_the_label() {
+        let map = SourceMap::parse(SOURCE).unwrap();
+        let location = map.locate("/a~1b").unwrap();
+        assert_eq!(
+            code_frame(SOURCE, "questions_x.json", &location, "in question 'q1'"),
+            [
+                " --> questions_x.json:3:10",
+                "  |",
+                "2 |   \"metadata\": {\"title\": \"Ross Sea\"},",
+                "3 |   \"a/b\": 1,",
+                "  |          ^ in question 'q1'",
+                "4 |   \"c~d\": [true, null],",
+                "  |",
+            ]
+            .join("\n")
+        );
+    }
+}
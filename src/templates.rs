@@ -0,0 +1,405 @@
+//! Handlebars setup for the bundled page.
+//!
+//! The page used to come from one monolithic `templates/index.hbs`, so
+//! changing a heading or the footer meant forking all of it. The template
+//! now includes partials, and everything a deployment may want to change can
+//! be overridden without touching the originals:
+//!
+//! - **Partials**: every `*.hbs` file under `paths.partials`
+//!   (`templates/partials/`) is registered under its path relative to that
+//!   directory, without the extension: `finish-screen.hbs` is
+//!   `{{> finish-screen}}`.
+//! - **Strings**: the page's own text lives in `paths.strings/<lang>.json`
+//!   (`templates/strings/en.json`), a flat object of keys to text, and is
+//!   inserted with the `t` helper. The page also embeds the whole catalogue
+//!   as `window.STRINGS`, for the text `ui.js` writes while the game runs.
+//! - **Themes**: `paths.theme` (`bundle --theme DIR`) names a directory that
+//!   may contain an `index.hbs` (or `sets.hbs`, the index of split bundles)
+//!   replacing the template of that name, a `partials/` directory whose
+//!   files replace partials of the same name or add new ones, a
+//!   `strings/<lang>.json` overriding individual strings, and a `style.css`
+//!   embedded after the base stylesheets (see [`theme_stylesheet`]).
+//!
+//! Besides the built-in helpers, templates can use:
+//!
+//! - `{{{json value}}}`: `value` as JSON that is safe inside a `<script>`
+//!   block (see [`script_json`]).
+//! - `{{asset "KR.png"}}`: the URL of an image under `paths.asset_root` as
+//!   the bundle serves it: optimised or inlined like the question images, a
+//!   data URI when images are inlined, the name as given otherwise.
+//! - `{{attribution}}`: the `[attribution]` credits from `game.toml`, with
+//!   the source site as a link. `{{attribution "source-url"}}` gives one of
+//!   them on its own (`copyright`, `source-name`, `source-url`, `credit`).
+//! - `{{t "finish.title"}}`: the string for the key in the page language.
+//!   `{name}` placeholders are filled from hash arguments,
+//!   `{{t "counter" current=1 total=6}}`. A missing key is a build error.
+//!
+//! `{{...}}` output is HTML-escaped (including `json` and `t`); only
+//! `{{{...}}}` is raw.
+
+use crate::config::{Attribution, Config};
+use crate::images::{data_uri, mime_type};
+use anyhow::{anyhow, Context as _, Result};
+use handlebars::{
+    html_escape, Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext,
+    RenderError, RenderErrorReason, ScopedJson,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PAGE: &str = "page";
+
+/// The page template with its partials and helpers registered.
+pub struct PageTemplate {
+    handlebars: Handlebars<'static>,
+    sources: BTreeMap<String, String>,
+    text: BTreeMap<String, String>,
+    lang: String,
+    /// The template file used, the base one or the theme's.
+    pub template: PathBuf,
+    /// Partial names, with the file each was read from.
+    pub partials: BTreeMap<String, PathBuf>,
+    /// The strings files read, base first.
+    pub strings: Vec<PathBuf>,
+}
+
+impl PageTemplate {
+    /// Loads `template` with the partials and strings `config` points at,
+    /// and the theme's overrides applied. `images` maps the question images
+    /// the bundle processed to their URL in the page, for the `asset`
+    /// helper.
+    pub fn load(config: &Config, template: &Path, images: BTreeMap<String, String>) -> Result<Self> {
+        let (paths, lang) = (&config.paths, config.bundle.lang.as_str());
+        let theme = paths.theme.as_deref();
+        if let Some(theme) = theme.filter(|t| !t.is_dir()) {
+            anyhow::bail!("Theme directory {} not found", theme.display());
+        }
+
+        let mut handlebars = Handlebars::new();
+        let mut sources = BTreeMap::new();
+
+        let template = theme
+            .zip(template.file_name())
+            .map(|(theme, name)| theme.join(name))
+            .filter(|t| t.is_file())
+            .unwrap_or_else(|| template.to_path_buf());
+        let source = fs::read_to_string(&template)
+            .with_context(|| format!("Template not found at {}", template.display()))?;
+        handlebars
+            .register_template_string(PAGE, &source)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", template.display(), e))?;
+        sources.insert("template".to_string(), source);
+
+        // Theme partials are collected last, so they win
+        let mut partials = BTreeMap::new();
+        collect_partials(&paths.partials, &paths.partials, &mut partials)?;
+        if let Some(theme) = theme {
+            let dir = theme.join("partials");
+            collect_partials(&dir, &dir, &mut partials)?;
+        }
+        for (name, path) in &partials {
+            let source = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read partial {}", path.display()))?;
+            handlebars
+                .register_partial(name, &source)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+            sources.insert(format!("partial:{}", name), source);
+        }
+
+        let base_strings = paths.strings.join(format!("{}.json", lang));
+        if !base_strings.is_file() {
+            anyhow::bail!(
+                "No strings for language '{}': {} not found",
+                lang,
+                base_strings.display()
+            );
+        }
+        let mut strings_files = vec![base_strings];
+        strings_files.extend(
+            theme
+                .map(|t| t.join("strings").join(format!("{}.json", lang)))
+                .filter(|p| p.is_file()),
+        );
+        let mut strings = BTreeMap::new();
+        for path in &strings_files {
+            strings.extend(read_strings(path)?);
+        }
+        sources.insert("strings".to_string(), serde_json::to_string(&strings)?);
+        sources.insert("attribution".to_string(), serde_json::to_string(&config.attribution)?);
+
+        handlebars.register_helper("json", Box::new(JsonHelper));
+        handlebars.register_helper(
+            "t",
+            Box::new(TranslateHelper {
+                strings: strings.clone(),
+                lang: lang.to_string(),
+            }),
+        );
+        handlebars.register_helper("attribution", Box::new(AttributionHelper(config.attribution.clone())));
+        handlebars.register_helper(
+            "asset",
+            Box::new(AssetHelper {
+                images,
+                root: paths.asset_root.clone(),
+                inline: config.bundle.inline_images,
+            }),
+        );
+
+        Ok(Self {
+            handlebars,
+            sources,
+            text: strings,
+            lang: lang.to_string(),
+            template,
+            partials,
+            strings: strings_files,
+        })
+    }
+
+    /// Every template, partial and string the page is made from, by name.
+    /// Part of the content hash.
+    pub fn sources(&self) -> &BTreeMap<String, String> {
+        &self.sources
+    }
+
+    /// Every string in the page language, theme overrides applied. The page
+    /// embeds them as `window.STRINGS` for the text its scripts write.
+    pub fn catalogue(&self) -> &BTreeMap<String, String> {
+        &self.text
+    }
+
+    /// The string for `key` in the page language, as `{{t "key"}}` gives it.
+    pub fn text(&self, key: &str) -> Result<&str> {
+        self.text
+            .get(key)
+            .map(String::as_str)
+            .with_context(|| format!("No '{}' string for language '{}'", key, self.lang))
+    }
+
+    pub fn render(&self, data: &Value) -> Result<String> {
+        // The tools print errors without their cause chain, so keep the
+        // template position in the message
+        self.handlebars
+            .render(PAGE, data)
+            .map_err(|e| anyhow!("Failed to render {}: {}", self.template.display(), e))
+    }
+}
+
+/// The theme's stylesheet, embedded after `paths.css`, if it has one.
+pub fn theme_stylesheet(config: &Config) -> Option<PathBuf> {
+    let stylesheet = config.paths.theme.as_ref()?.join("style.css");
+    stylesheet.is_file().then_some(stylesheet)
+}
+
+/// Adds every `*.hbs` under `dir` to `partials`, named by its path relative
+/// to `root` without the extension. A missing directory has no partials.
+fn collect_partials(root: &Path, dir: &Path, partials: &mut BTreeMap<String, PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_partials(root, &path, partials)?;
+        } else if path.extension().is_some_and(|e| e == "hbs") {
+            let name = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            partials.insert(name, path);
+        }
+    }
+    Ok(())
+}
+
+fn read_strings(path: &Path) -> Result<BTreeMap<String, String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).map_err(|e| {
+        anyhow!(
+            "Failed to parse {}: {} (expected an object of keys to strings)",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Escapes JSON for inclusion in a `<script>` block.
+///
+/// The HTML parser ends a script at the first `</script`, whatever JS string
+/// it is in, and `<!--` switches it into a mode where that can go wrong too;
+/// so `<`, `>` and `&` are written as `\u003c`, `\u003e` and `\u0026`. U+2028
+/// and U+2029 are valid in JSON strings but were line terminators in JS
+/// string literals before ES2019. All of these only occur inside strings,
+/// where the escapes decode to the same text.
+pub fn script_json(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        match c {
+            '<' => escaped.push_str("\\u003c"),
+            '>' => escaped.push_str("\\u003e"),
+            '&' => escaped.push_str("\\u0026"),
+            '\u{2028}' => escaped.push_str("\\u2028"),
+            '\u{2029}' => escaped.push_str("\\u2029"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn helper_error(message: String) -> RenderError {
+    RenderErrorReason::NestedError(anyhow!(message).into()).into()
+}
+
+/// The first parameter of `h`, which `name` requires.
+fn first_param<'a>(h: &'a Helper, name: &'static str) -> Result<&'a Value, RenderError> {
+    h.param(0)
+        .map(|p| p.value())
+        .ok_or_else(|| RenderErrorReason::ParamNotFoundForIndex(name, 0).into())
+}
+
+/// `{{{json value}}}`
+struct JsonHelper;
+
+impl HelperDef for JsonHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let json = serde_json::to_string(first_param(h, "json")?)
+            .map_err(|e| helper_error(e.to_string()))?;
+        Ok(ScopedJson::Derived(Value::String(script_json(&json))))
+    }
+}
+
+/// `{{t "key" name=value}}`
+struct TranslateHelper {
+    strings: BTreeMap<String, String>,
+    lang: String,
+}
+
+impl HelperDef for TranslateHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let key = first_param(h, "t")?
+            .as_str()
+            .ok_or(RenderErrorReason::InvalidParamType("string"))?;
+        let mut text = self
+            .strings
+            .get(key)
+            .ok_or_else(|| helper_error(format!("No '{}' string for language '{}'", key, self.lang)))?
+            .clone();
+        for (name, value) in h.hash() {
+            let value = match value.value() {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            text = text.replace(&format!("{{{}}}", name), &value);
+        }
+        Ok(ScopedJson::Derived(Value::String(text)))
+    }
+}
+
+/// `{{attribution}}`, which writes markup and escapes the credits itself,
+/// and `{{attribution "source-url"}}`, which writes one of them.
+struct AttributionHelper(Attribution);
+
+impl HelperDef for AttributionHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let credits = &self.0;
+        if let Some(field) = h.param(0) {
+            let field = field
+                .value()
+                .as_str()
+                .ok_or(RenderErrorReason::InvalidParamType("string"))?;
+            let value = match field {
+                "copyright" => &credits.copyright,
+                "source-name" => &credits.source_name,
+                "source-url" => &credits.source_url,
+                "credit" => &credits.credit,
+                _ => {
+                    return Err(helper_error(format!(
+                        "Unknown attribution field '{}' (known: copyright, source-name, source-url, credit)",
+                        field
+                    )))
+                }
+            };
+            out.write(&html_escape(value))?;
+            return Ok(());
+        }
+        let mut parts = Vec::new();
+        if !credits.copyright.is_empty() {
+            parts.push(html_escape(&credits.copyright));
+        }
+        match (credits.source_name.is_empty(), credits.source_url.is_empty()) {
+            (false, false) => parts.push(format!(
+                "<a href=\"{}\" target=\"_blank\">{}</a>",
+                html_escape(&credits.source_url),
+                html_escape(&credits.source_name)
+            )),
+            (false, true) => parts.push(html_escape(&credits.source_name)),
+            _ => {}
+        }
+        if !credits.credit.is_empty() {
+            parts.push(html_escape(&credits.credit));
+        }
+        out.write(&parts.join(" | "))?;
+        Ok(())
+    }
+}
+
+/// `{{asset "name"}}`
+struct AssetHelper {
+    /// URLs of the images the bundle already processed.
+    images: BTreeMap<String, String>,
+    root: PathBuf,
+    inline: bool,
+}
+
+impl HelperDef for AssetHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let name = first_param(h, "asset")?
+            .as_str()
+            .ok_or(RenderErrorReason::InvalidParamType("string"))?;
+        let url = match self.images.get(name) {
+            Some(url) => url.clone(),
+            None if self.inline => {
+                let path = self.root.join(name);
+                let bytes = fs::read(&path)
+                    .map_err(|e| helper_error(format!("Failed to read asset {}: {}", path.display(), e)))?;
+                data_uri(&bytes, mime_type(name).map_err(|e| helper_error(e.to_string()))?)
+            }
+            None => name.to_string(),
+        };
+        Ok(ScopedJson::Derived(Value::String(url)))
+    }
+}
@@ -0,0 +1,305 @@
+//! Results of `test-runner` runs, and their machine-readable renderings.
+//!
+//! `test-runner --report junit=PATH` and `--report json=PATH` write these so
+//! CI systems can show the history of every question set, and of every step
+//! within it, rather than only the colored summary.
+
+use crate::report::xml_escape;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Status {
+    Passed,
+    Failed,
+    /// Not started because too many sets had already failed.
+    NotRun,
+}
+
+/// One timed step of playing a set, such as answering one question.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub name: String,
+    pub duration: Duration,
+    /// Why the step failed, if it did.
+    pub error: Option<String>,
+}
+
+/// The outcome of playing one question set.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    /// Key of the question set, e.g. `questions_ross_sea_easy`.
+    pub key: String,
+    /// Title of the question set.
+    pub name: String,
+    pub mode: String,
+    pub status: Status,
+    pub error: Option<String>,
+    pub duration: Duration,
+    /// The steps run, in order; the last one failed if the set did.
+    pub steps: Vec<Step>,
+    /// Screenshots taken while playing.
+    pub screenshots: Vec<PathBuf>,
+    /// Errors the page reported: console errors, uncaught exceptions and
+    /// resources that failed to load.
+    pub console_errors: Vec<String>,
+}
+
+impl TestResult {
+    fn count(results: &[TestResult], status: Status) -> usize {
+        results.iter().filter(|r| r.status == status).count()
+    }
+}
+
+/// Plain JSON with camelCase keys, like the other reports: every set with
+/// its steps, plus totals. Times are in seconds.
+pub fn to_json(results: &[TestResult], elapsed: Duration) -> Value {
+    let sets: Vec<Value> = results
+        .iter()
+        .map(|r| {
+            let steps: Vec<Value> = r
+                .steps
+                .iter()
+                .map(|s| {
+                    json!({
+                        "name": s.name,
+                        "seconds": s.duration.as_secs_f64(),
+                        "error": s.error,
+                    })
+                })
+                .collect();
+            json!({
+                "key": r.key,
+                "title": r.name,
+                "mode": r.mode,
+                "status": r.status,
+                "seconds": r.duration.as_secs_f64(),
+                "error": r.error,
+                "steps": steps,
+                "screenshots": r.screenshots,
+                "consoleErrors": r.console_errors,
+            })
+        })
+        .collect();
+
+    json!({
+        "sets": sets,
+        "summary": {
+            "sets": results.len(),
+            "passed": TestResult::count(results, Status::Passed),
+            "failed": TestResult::count(results, Status::Failed),
+            "notRun": TestResult::count(results, Status::NotRun),
+            "seconds": elapsed.as_secs_f64(),
+        }
+    })
+}
+
+/// JUnit XML: a test suite per set and a test case per step. Screenshots
+/// are listed in `<system-out>` as `[[ATTACHMENT|path]]`, which Jenkins and
+/// GitLab pick up, and page errors go to `<system-err>`.
+pub fn to_junit(results: &[TestResult], elapsed: Duration) -> String {
+    let mut out = String::new();
+    let (mut tests, mut failures) = (0, 0);
+    for result in results {
+        let classname = xml_escape(&result.key);
+        let mut cases = Vec::new();
+        let mut case_failures = 0;
+        for step in &result.steps {
+            let name = xml_escape(&step.name);
+            let time = step.duration.as_secs_f64();
+            match &step.error {
+                None => cases.push(format!(
+                    "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"/>",
+                    classname, name, time
+                )),
+                Some(error) => {
+                    case_failures += 1;
+                    cases.push(format!(
+                        "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">\n      {}\n    </testcase>",
+                        classname,
+                        name,
+                        time,
+                        failure(error)
+                    ));
+                }
+            }
+        }
+        let title = xml_escape(&result.name);
+        match (&result.status, &result.error) {
+            (Status::NotRun, _) => cases.push(format!(
+                "    <testcase classname=\"{}\" name=\"{}\">\n      <skipped message=\"not run\"/>\n    </testcase>",
+                classname, title
+            )),
+            // Failed outside any step: report it against the set as a whole
+            (Status::Failed, Some(error)) if case_failures == 0 => {
+                case_failures += 1;
+                cases.push(format!(
+                    "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">\n      {}\n    </testcase>",
+                    classname,
+                    title,
+                    result.duration.as_secs_f64(),
+                    failure(error)
+                ));
+            }
+            _ => {}
+        }
+
+        tests += cases.len();
+        failures += case_failures;
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+            classname,
+            cases.len(),
+            case_failures,
+            usize::from(result.status == Status::NotRun),
+            result.duration.as_secs_f64()
+        );
+        out.push_str("    <properties>\n");
+        let _ = writeln!(out, "      <property name=\"title\" value=\"{}\"/>", title);
+        let _ = writeln!(out, "      <property name=\"mode\" value=\"{}\"/>", xml_escape(&result.mode));
+        out.push_str("    </properties>\n");
+        for case in cases {
+            out.push_str(&case);
+            out.push('\n');
+        }
+        if !result.screenshots.is_empty() {
+            let attachments: Vec<String> = result
+                .screenshots
+                .iter()
+                .map(|path| format!("[[ATTACHMENT|{}]]", path.display()))
+                .collect();
+            let _ = writeln!(out, "    <system-out>{}</system-out>", xml_escape(&attachments.join("\n")));
+        }
+        if !result.console_errors.is_empty() {
+            let _ = writeln!(
+                out,
+                "    <system-err>{}</system-err>",
+                xml_escape(&result.console_errors.join("\n"))
+            );
+        }
+        out.push_str("  </testsuite>\n");
+    }
+
+    out.push_str("</testsuites>\n");
+
+    let header = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"test-runner\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        tests,
+        failures,
+        TestResult::count(results, Status::NotRun),
+        elapsed.as_secs_f64()
+    );
+    header + &out
+}
+
+fn failure(error: &str) -> String {
+    let message = error.lines().next().unwrap_or("");
+    format!(
+        "<failure message=\"{}\">{}</failure>",
+        xml_escape(message),
+        xml_escape(error)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(key: &str, status: Status, error: Option<&str>, steps: Vec<Step>) -> TestResult {
+        TestResult {
+            key: key.to_string(),
+            name: format!("{} title", key),
+            mode: "hard".to_string(),
+            status,
+            error: error.map(str::to_string),
+            duration: Duration::from_millis(1500),
+            steps,
+            screenshots: Vec::new(),
+            console_errors: Vec::new(),
+        }
+    }
+
+    fn step(name: &str, error: Option<&str>) -> Step {
+        Step {
+            name: name.to_string(),
+            duration: Duration::from_millis(250),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn junit_marks_sets_not_run_as_skipped() {
+        let xml = to_junit(&[result("questions_a", Status::NotRun, None, Vec::new())], Duration::ZERO);
+        assert!(xml.contains("<testsuites name=\"test-runner\" tests=\"1\" failures=\"0\" skipped=\"1\""));
+        assert!(xml.contains("tests=\"1\" failures=\"0\" errors=\"0\" skipped=\"1\""));
+        assert!(xml.contains(
+            "<testcase classname=\"questions_a\" name=\"questions_a title\">\n      <skipped message=\"not run\"/>"
+        ));
+    }
+
+    #[test]
+    fn junit_reports_failures_against_their_step() {
+        let steps = vec![step("load page", None), step("question 1/2 (q1)", Some("Timeout"))];
+        let xml = to_junit(&[result("questions_a", Status::Failed, Some("Timeout"), steps)], Duration::ZERO);
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<testcase classname=\"questions_a\" name=\"load page\" time=\"0.250\"/>"));
+        assert!(xml.contains(
+            "name=\"question 1/2 (q1)\" time=\"0.250\">\n      <failure message=\"Timeout\">Timeout</failure>"
+        ));
+        // The set's own error is not reported a second time
+        assert!(!xml.contains("name=\"questions_a title\""));
+    }
+
+    #[test]
+    fn junit_reports_failures_outside_any_step_against_the_set() {
+        let steps = vec![step("load page", None)];
+        let error = "Failed to open page\nmore detail";
+        let xml = to_junit(&[result("questions_a", Status::Failed, Some(error), steps)], Duration::ZERO);
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains(
+            "name=\"questions_a title\" time=\"1.500\">\n      <failure message=\"Failed to open page\">Failed to open page\nmore detail</failure>"
+        ));
+    }
+
+    #[test]
+    fn junit_escapes_names_messages_and_output() {
+        let mut r = result("a<b>", Status::Failed, Some("\"x\" & 'y'"), vec![step("q & <a>", Some("1 < 2"))]);
+        r.console_errors = vec!["console.error: <oops> & more".to_string()];
+        r.screenshots = vec![PathBuf::from("out/a&b.png")];
+        let xml = to_junit(&[r], Duration::ZERO);
+        assert!(xml.contains("<testsuite name=\"a&lt;b&gt;\""));
+        assert!(xml.contains("name=\"q &amp; &lt;a&gt;\""));
+        assert!(xml.contains("<failure message=\"1 &lt; 2\">1 &lt; 2</failure>"));
+        assert!(xml.contains("<system-out>[[ATTACHMENT|out/a&amp;b.png]]</system-out>"));
+        assert!(xml.contains("<system-err>console.error: &lt;oops&gt; &amp; more</system-err>"));
+        assert!(!xml.contains("<oops>"));
+    }
+
+    #[test]
+    fn json_lists_sets_steps_and_totals() {
+        let results = [
+            result("questions_a", Status::Passed, None, vec![step("load page", None)]),
+            result("questions_b", Status::Failed, Some("boom"), vec![step("load page", Some("boom"))]),
+            result("questions_c", Status::NotRun, None, Vec::new()),
+        ];
+        let json = to_json(&results, Duration::from_secs(3));
+        assert_eq!(
+            json["summary"],
+            json!({"sets": 3, "passed": 1, "failed": 1, "notRun": 1, "seconds": 3.0})
+        );
+        assert_eq!(json["sets"][1]["status"], "failed");
+        assert_eq!(json["sets"][2]["status"], "notRun");
+        assert_eq!(json["sets"][1]["steps"][0], json!({"name": "load page", "seconds": 0.25, "error": "boom"}));
+        assert_eq!(json["sets"][0]["error"], Value::Null);
+        let keys: Vec<&str> = json["sets"][0].as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            ["key", "title", "mode", "status", "seconds", "error", "steps", "screenshots", "consoleErrors"]
+        );
+    }
+}
@@ -0,0 +1,556 @@
+//! Rule-based semantic checks for question sets.
+//!
+//! JSON Schema can only check shape: it happily accepts a `correctAnswer` of
+//! 7 for a question with four choices. The rules here run after schema
+//! validation on the typed model and catch mistakes that would break or
+//! confuse the game.
+
+use crate::model::{Difficulty, Mode, QuestionSetFile};
+use crate::source_map::{Location, SourceMap};
+use jsonschema::JSONSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The file is not well-formed JSON.
+pub const RULE_JSON_SYNTAX: &str = "json-syntax";
+/// The file does not match `data/schema.json`.
+pub const RULE_SCHEMA: &str = "schema";
+/// `correctAnswer` must index into `choices`.
+pub const RULE_CORRECT_ANSWER_RANGE: &str = "correct-answer-range";
+/// Two choices of the same question have the same text.
+pub const RULE_DUPLICATE_CHOICE: &str = "duplicate-choice";
+/// A question `id` is used more than once, within or across files.
+pub const RULE_DUPLICATE_ID: &str = "duplicate-id";
+/// The explanation shown on the result screen is blank.
+pub const RULE_EMPTY_EXPLANATION: &str = "empty-explanation";
+/// A question's `difficulty` does not fit the set's `metadata.mode`.
+pub const RULE_MODE_DIFFICULTY: &str = "mode-difficulty";
+/// Question text contains HTML tags or character references, which the game
+/// shows literally.
+pub const RULE_HTML_MARKUP: &str = "html-markup";
+/// The correct answer contradicts the food web (`validate --check-foodweb`).
+pub const RULE_FOODWEB_CONTRADICTION: &str = "foodweb-contradiction";
+/// A distractor is also correct according to the food web (`validate --check-foodweb`).
+pub const RULE_FOODWEB_DISTRACTOR: &str = "foodweb-distractor";
+
+/// Every rule id with a one-line description, for report formats that
+/// describe their rules up front (SARIF).
+pub const RULES: &[(&str, &str)] = &[
+    (RULE_JSON_SYNTAX, "The file is not well-formed JSON"),
+    (RULE_SCHEMA, "The file does not match data/schema.json"),
+    (RULE_CORRECT_ANSWER_RANGE, "correctAnswer must index into choices"),
+    (RULE_DUPLICATE_CHOICE, "Choices of a question must be distinct"),
+    (RULE_DUPLICATE_ID, "Question ids must be unique across all question sets"),
+    (RULE_EMPTY_EXPLANATION, "Every question needs an explanation"),
+    (RULE_MODE_DIFFICULTY, "Question difficulty should fit the set's mode"),
+    (RULE_HTML_MARKUP, "Question text must be plain text, not HTML"),
+    (RULE_FOODWEB_CONTRADICTION, "The correct answer must agree with the food web"),
+    (RULE_FOODWEB_DISTRACTOR, "Distractors must not also be correct according to the food web"),
+];
+
+/// How serious a finding is. Only errors fail validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single finding from a validation rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    /// The question-set file the finding is in.
+    pub file: PathBuf,
+    /// The question the finding is about, if it is about one question.
+    pub question_id: Option<String>,
+    /// JSON pointer to the offending value (empty for the whole document).
+    pub pointer: String,
+    /// Position of the offending value in the file, once resolved with
+    /// [`locate_diagnostics`].
+    pub location: Option<Location>,
+    /// Rule identifier, one of the `RULE_*` constants.
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(
+        file: &Path,
+        question_id: Option<&str>,
+        pointer: impl Into<String>,
+        rule: &'static str,
+        severity: Severity,
+        message: String,
+    ) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            question_id: question_id.map(str::to_string),
+            pointer: pointer.into(),
+            location: None,
+            rule,
+            severity,
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Parses `source` as JSON, turning a syntax error into a located diagnostic.
+pub fn check_syntax(file: &Path, source: &str) -> Result<Value, Box<Diagnostic>> {
+    serde_json::from_str(source).map_err(|e| {
+        let mut diagnostic = Diagnostic::new(
+            file,
+            None,
+            "",
+            RULE_JSON_SYNTAX,
+            Severity::Error,
+            e.to_string(),
+        );
+        diagnostic.location = Some(SourceMap::location_at(source, e.line(), e.column()));
+        Box::new(diagnostic)
+    })
+}
+
+/// Validates `instance` against the compiled schema, one diagnostic per
+/// schema violation.
+pub fn check_schema(file: &Path, instance: &Value, schema: &JSONSchema) -> Vec<Diagnostic> {
+    match schema.validate(instance) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|error| {
+                let pointer = error.instance_path.to_string();
+                let question_id = question_id_at(instance, &pointer);
+                Diagnostic::new(
+                    file,
+                    question_id,
+                    pointer,
+                    RULE_SCHEMA,
+                    Severity::Error,
+                    error.to_string(),
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Fills in [`Diagnostic::location`] for the diagnostics in `file` that have
+/// none yet, using that file's source text.
+pub fn locate_diagnostics(diagnostics: &mut [Diagnostic], file: &Path, source: &str) {
+    let Some(map) = SourceMap::parse(source) else {
+        return;
+    };
+    for diagnostic in diagnostics
+        .iter_mut()
+        .filter(|d| d.file == file && d.location.is_none())
+    {
+        diagnostic.location = map.locate(&diagnostic.pointer);
+    }
+}
+
+/// The `id` of the question a pointer such as `/questions/3/choices/1` falls in.
+fn question_id_at<'a>(instance: &'a Value, pointer: &str) -> Option<&'a str> {
+    let mut segments = pointer.split('/').skip(1);
+    if segments.next()? != "questions" {
+        return None;
+    }
+    let index: usize = segments.next()?.parse().ok()?;
+    instance.get("questions")?.get(index)?.get("id")?.as_str()
+}
+
+/// Runs every semantic rule over `files` and returns the findings in file,
+/// then question order.
+///
+/// The files are checked together so that duplicate ids can be detected
+/// across question sets; pass every set that ends up in the same bundle.
+pub fn check_question_sets(files: &[QuestionSetFile]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // id -> (file index, question index) of its first use
+    let mut seen_ids: HashMap<&str, (usize, usize)> = HashMap::new();
+
+    for (file_idx, file) in files.iter().enumerate() {
+        let mode = file.set.metadata.mode;
+        let metadata = &file.set.metadata;
+        let metadata_fields = [("title", Some(&metadata.title)), ("description", metadata.description.as_ref())];
+        for (field, text) in metadata_fields {
+            if let Some(markup) = text.and_then(|t| find_markup(t)) {
+                diagnostics.push(Diagnostic::new(
+                    &file.path,
+                    None,
+                    format!("/metadata/{}", field),
+                    RULE_HTML_MARKUP,
+                    Severity::Error,
+                    markup_message(field, markup),
+                ));
+            }
+        }
+
+        for (q_idx, question) in file.set.questions.iter().enumerate() {
+            let id = question.id.as_str();
+            let base = format!("/questions/{}", q_idx);
+            let mut report = |field: &str, rule, severity, message| {
+                diagnostics.push(Diagnostic::new(
+                    &file.path,
+                    Some(id),
+                    format!("{}/{}", base, field),
+                    rule,
+                    severity,
+                    message,
+                ));
+            };
+
+            match seen_ids.get(id) {
+                Some(&(first_file, first_q)) if first_file == file_idx => report(
+                    "id",
+                    RULE_DUPLICATE_ID,
+                    Severity::Error,
+                    format!(
+                        "id '{}' is also used by question {} in this file",
+                        id,
+                        first_q + 1
+                    ),
+                ),
+                Some(&(first_file, _)) => report(
+                    "id",
+                    RULE_DUPLICATE_ID,
+                    Severity::Error,
+                    format!(
+                        "id '{}' is already used in {}",
+                        id,
+                        files[first_file].filename()
+                    ),
+                ),
+                None => {
+                    seen_ids.insert(id, (file_idx, q_idx));
+                }
+            }
+
+            if question.correct_answer >= question.choices.len() {
+                report(
+                    "correctAnswer",
+                    RULE_CORRECT_ANSWER_RANGE,
+                    Severity::Error,
+                    format!(
+                        "correctAnswer is {} but there are only {} choices (valid: 0..={})",
+                        question.correct_answer,
+                        question.choices.len(),
+                        question.choices.len().saturating_sub(1)
+                    ),
+                );
+            }
+
+            let mut choice_texts = HashSet::new();
+            for (c_idx, choice) in question.choices.iter().enumerate() {
+                let normalized = choice.trim().to_lowercase();
+                if !choice_texts.insert(normalized) {
+                    report(
+                        &format!("choices/{}", c_idx),
+                        RULE_DUPLICATE_CHOICE,
+                        Severity::Error,
+                        format!("choice '{}' appears more than once", choice.trim()),
+                    );
+                }
+            }
+
+            if question.explanation.trim().is_empty() {
+                report(
+                    "explanation",
+                    RULE_EMPTY_EXPLANATION,
+                    Severity::Error,
+                    "explanation is empty".to_string(),
+                );
+            }
+
+            let text_fields = [
+                ("question".to_string(), &question.question),
+                ("explanation".to_string(), &question.explanation),
+                ("category".to_string(), &question.category),
+            ]
+            .into_iter()
+            .chain(question.choices.iter().enumerate().map(|(i, c)| (format!("choices/{}", i), c)))
+            .chain(question.tags.iter().enumerate().map(|(i, t)| (format!("tags/{}", i), t)));
+            for (field, text) in text_fields {
+                if let Some(markup) = find_markup(text) {
+                    let message = markup_message(&field, markup);
+                    report(&field, RULE_HTML_MARKUP, Severity::Error, message);
+                }
+            }
+
+            if let Some(difficulty) = question.difficulty {
+                let mismatched = matches!(
+                    (mode, difficulty),
+                    (Mode::Easy, Difficulty::Hard) | (Mode::Hard, Difficulty::Easy)
+                );
+                if mismatched {
+                    report(
+                        "difficulty",
+                        RULE_MODE_DIFFICULTY,
+                        Severity::Warning,
+                        format!(
+                            "difficulty is '{}' but the set's mode is '{}'",
+                            difficulty, mode
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// The first HTML tag (`<b>`, `</p>`, `<br/>`), comment or character
+/// reference (`&amp;`, `&#39;`, `&#x27;`) in `text`. A `<` not followed by a tag name,
+/// as in "2 < 3" or "<3", is not markup.
+pub fn find_markup(text: &str) -> Option<&str> {
+    for (start, c) in text.char_indices() {
+        let rest = &text[start..];
+        match c {
+            '<' => {
+                if rest.starts_with("<!--") {
+                    return Some(&rest[..4]);
+                }
+                let name = rest[1..].strip_prefix('/').unwrap_or(&rest[1..]);
+                let name_len = name
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(name.len());
+                let starts_tag = name.starts_with(|c: char| c.is_ascii_alphabetic());
+                let after_name = &name[name_len..];
+                let ends_tag = after_name.starts_with(['>', '/', ' ', '\t', '\n'])
+                    && after_name.find('>').is_some_and(|end| !after_name[..end].contains('<'));
+                if starts_tag && ends_tag {
+                    let end = rest.find('>').map_or(rest.len(), |e| e + 1);
+                    return Some(&rest[..end]);
+                }
+            }
+            '&' => {
+                let body = &rest[1..];
+                let len = body.find(';').unwrap_or(0);
+                let reference = &body[..len];
+                let named = !reference.is_empty() && reference.chars().all(|c| c.is_ascii_alphanumeric());
+                let numeric = reference.strip_prefix('#').is_some_and(|n| match n.strip_prefix(['x', 'X']) {
+                    Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                    None => !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()),
+                });
+                if len <= 32 && (named || numeric) {
+                    return Some(&rest[..len + 2]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn markup_message(field: &str, markup: &str) -> String {
+    format!(
+        "{} contains HTML markup '{}'; the game shows text as-is, so write plain text \
+         (or allow the rule with `html-markup = \"allow\"` under [lint.rules] in game.toml)",
+        field, markup
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Metadata, Question, QuestionSet};
+
+    fn question(id: &str) -> Question {
+        Question {
+            id: id.to_string(),
+            question: "What do penguins eat?".to_string(),
+            image1: None,
+            image2: None,
+            choices: vec!["Krill".to_string(), "Seals".to_string(), "Whales".to_string()],
+            correct_answer: 0,
+            explanation: "Penguins eat krill.".to_string(),
+            category: "food-chain".to_string(),
+            difficulty: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn file(name: &str, mode: Mode, questions: Vec<Question>) -> QuestionSetFile {
+        QuestionSetFile {
+            key: name.trim_end_matches(".json").to_string(),
+            path: PathBuf::from(name),
+            set: QuestionSet {
+                metadata: Metadata {
+                    title: "Ross Sea Food Web".to_string(),
+                    description: None,
+                    version: "1.0.0".to_string(),
+                    target_age: "5-8 years".to_string(),
+                    subject: "Marine Biology".to_string(),
+                    mode,
+                },
+                questions,
+            },
+        }
+    }
+
+    fn findings(diagnostics: &[Diagnostic]) -> Vec<(&str, Severity, &str, &str)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.file.to_str().unwrap(), d.severity, d.rule, d.pointer.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_clean_set() {
+        let files = [file("questions_easy.json", Mode::Easy, vec![question("q1"), question("q2")])];
+        assert_eq!(check_question_sets(&files), Vec::new());
+    }
+
+    #[test]
+    fn finds_duplicate_ids_within_and_across_sets() {
+        let files = [
+            file("questions_easy.json", Mode::Easy, vec![question("q1"), question("q1")]),
+            file("questions_hard.json", Mode::Hard, vec![question("q2"), question("q1")]),
+        ];
+        let diagnostics = check_question_sets(&files);
+        assert_eq!(
+            findings(&diagnostics),
+            vec![
+                ("questions_easy.json", Severity::Error, RULE_DUPLICATE_ID, "/questions/1/id"),
+                ("questions_hard.json", Severity::Error, RULE_DUPLICATE_ID, "/questions/1/id"),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "id 'q1' is also used by question 1 in this file");
+        assert_eq!(diagnostics[1].message, "id 'q1' is already used in questions_easy.json");
+        assert_eq!(diagnostics[1].question_id.as_deref(), Some("q1"));
+    }
+
+    #[test]
+    fn finds_a_correct_answer_out_of_range() {
+        let mut q = question("q1");
+        q.correct_answer = 3;
+        let diagnostics = check_question_sets(&[file("questions_easy.json", Mode::Easy, vec![q])]);
+        assert_eq!(
+            findings(&diagnostics),
+            vec![("questions_easy.json", Severity::Error, RULE_CORRECT_ANSWER_RANGE, "/questions/0/correctAnswer")]
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "correctAnswer is 3 but there are only 3 choices (valid: 0..=2)"
+        );
+    }
+
+    #[test]
+    fn finds_duplicate_choices_ignoring_case_and_spaces() {
+        let mut q = question("q1");
+        q.choices.push(" krill ".to_string());
+        let diagnostics = check_question_sets(&[file("questions_easy.json", Mode::Easy, vec![q])]);
+        assert_eq!(
+            findings(&diagnostics),
+            vec![("questions_easy.json", Severity::Error, RULE_DUPLICATE_CHOICE, "/questions/0/choices/3")]
+        );
+        assert_eq!(diagnostics[0].message, "choice 'krill' appears more than once");
+    }
+
+    #[test]
+    fn finds_a_blank_explanation() {
+        let mut q = question("q1");
+        q.explanation = " \n".to_string();
+        let diagnostics = check_question_sets(&[file("questions_easy.json", Mode::Easy, vec![q])]);
+        assert_eq!(
+            findings(&diagnostics),
+            vec![("questions_easy.json", Severity::Error, RULE_EMPTY_EXPLANATION, "/questions/0/explanation")]
+        );
+    }
+
+    #[test]
+    fn warns_about_difficulty_that_does_not_fit_the_mode() {
+        let labelled = |id: &str, difficulty| {
+            let mut q = question(id);
+            q.difficulty = Some(difficulty);
+            q
+        };
+        let files = [
+            file(
+                "questions_easy.json",
+                Mode::Easy,
+                vec![labelled("e1", Difficulty::Easy), labelled("e2", Difficulty::Medium), labelled("e3", Difficulty::Hard)],
+            ),
+            file(
+                "questions_hard.json",
+                Mode::Hard,
+                vec![labelled("h1", Difficulty::Easy), labelled("h2", Difficulty::Medium), labelled("h3", Difficulty::Hard)],
+            ),
+        ];
+        let diagnostics = check_question_sets(&files);
+        assert_eq!(
+            findings(&diagnostics),
+            vec![
+                ("questions_easy.json", Severity::Warning, RULE_MODE_DIFFICULTY, "/questions/2/difficulty"),
+                ("questions_hard.json", Severity::Warning, RULE_MODE_DIFFICULTY, "/questions/0/difficulty"),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "difficulty is 'hard' but the set's mode is 'easy'");
+        assert!(!diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn finds_tags_and_comments() {
+        assert_eq!(find_markup("Penguins <b>eat</b> krill"), Some("<b>"));
+        assert_eq!(find_markup("Krill</p>"), Some("</p>"));
+        assert_eq!(find_markup("line<br/>break"), Some("<br/>"));
+        assert_eq!(find_markup("an <img src=\"x.png\"> image"), Some("<img src=\"x.png\">"));
+        assert_eq!(find_markup("hidden <!-- note -->"), Some("<!--"));
+    }
+
+    #[test]
+    fn finds_character_references() {
+        assert_eq!(find_markup("Salt &amp; pepper"), Some("&amp;"));
+        assert_eq!(find_markup("It&#39;s krill"), Some("&#39;"));
+        assert_eq!(find_markup("It&#x27;s krill"), Some("&#x27;"));
+        assert_eq!(find_markup("It&#X2F;s krill"), Some("&#X2F;"));
+    }
+
+    #[test]
+    fn leaves_plain_text_alone() {
+        for text in [
+            "a < b & c",
+            "2 < 3 and 3 > 2",
+            "I <3 penguins",
+            "a <b and c",
+            "Fish & chips; krill",
+            "&#12a; is not a reference",
+            "&#; &#x; &;",
+        ] {
+            assert_eq!(find_markup(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn reports_markup_with_its_pointer() {
+        let mut q = question("q1");
+        q.choices[1] = "<i>Seals</i>".to_string();
+        let diagnostics = check_question_sets(&[file("questions_easy.json", Mode::Easy, vec![q])]);
+        assert_eq!(
+            findings(&diagnostics),
+            vec![("questions_easy.json", Severity::Error, RULE_HTML_MARKUP, "/questions/0/choices/1")]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! The whole validation run of `validate`.
+//!
+//! Each question file first goes through the per-file passes (JSON syntax,
+//! then the schema); the files that pass are then checked together by the
+//! semantic rules, which need all of them to catch duplicate ids between
+//! sets. Findings are graded by the `[lint]` configuration. `validate`
+//! prints the result and `serve` shows it in the page.
+
+use crate::config::Config;
+use crate::factcheck::check_food_web;
+use crate::foodweb::FoodWeb;
+use crate::report::FileReport;
+use crate::validation::{
+    check_question_sets, check_schema, check_syntax, locate_diagnostics, Diagnostic, Severity,
+    RULE_SCHEMA,
+};
+use crate::{discover_question_files, QuestionSet, QuestionSetFile};
+use anyhow::{anyhow, Context, Result};
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The outcome of the per-file passes (syntax and schema) for one file.
+#[derive(Debug, Clone)]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub source: String,
+    /// Title and question count, when the file parsed.
+    pub summary: Option<(String, usize)>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The typed set, when the file passed both passes.
+    pub set: Option<QuestionSetFile>,
+}
+
+impl FileResult {
+    pub fn filename(&self) -> &str {
+        self.path.file_name().and_then(|n| n.to_str()).unwrap_or("")
+    }
+}
+
+/// The compiled schema and the checks a configuration asks for.
+pub struct Validator {
+    pub schema_path: PathBuf,
+    schema: JSONSchema,
+    config: Config,
+}
+
+impl Validator {
+    /// Loads and compiles the schema `config` points at.
+    pub fn new(config: &Config) -> Result<Self> {
+        let data_dir = config.paths.data_dir.as_path();
+        if !data_dir.exists() {
+            anyhow::bail!("Data directory not found at: {}", data_dir.display());
+        }
+        let schema_path = config.paths.schema();
+        let schema_content = fs::read_to_string(&schema_path)
+            .with_context(|| format!("Failed to read schema at {}", schema_path.display()))?;
+        let schema: Value = serde_json::from_str(&schema_content)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", schema_path.display(), e))?;
+        let schema = JSONSchema::compile(&schema)
+            .map_err(|e| anyhow!("Failed to compile JSON schema {}: {}", schema_path.display(), e))?;
+        Ok(Self {
+            schema_path,
+            schema,
+            config: config.clone(),
+        })
+    }
+
+    /// Runs the syntax and schema passes on one file.
+    pub fn check_file(&self, path: &Path) -> Result<FileResult> {
+        let source = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut result = FileResult {
+            path: path.to_path_buf(),
+            source,
+            summary: None,
+            diagnostics: Vec::new(),
+            set: None,
+        };
+
+        let instance: Value = match check_syntax(path, &result.source) {
+            Ok(v) => v,
+            Err(diagnostic) => {
+                result.diagnostics.push(*diagnostic);
+                self.config.lint.apply(&mut result.diagnostics);
+                return Ok(result);
+            }
+        };
+
+        // Extract metadata for display
+        let title = instance
+            .get("metadata")
+            .and_then(|m| m.get("title"))
+            .and_then(|t| t.as_str())
+            .unwrap_or("No title");
+        let question_count = instance
+            .get("questions")
+            .and_then(|q| q.as_array())
+            .map(|a| a.len())
+            .unwrap_or(0);
+        result.summary = Some((title.to_string(), question_count));
+
+        result.diagnostics = check_schema(path, &instance, &self.schema);
+        if result.diagnostics.is_empty() {
+            // Keep the typed set for the semantic pass
+            match serde_json::from_value::<QuestionSet>(instance) {
+                Ok(set) => {
+                    let key = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+                    result.set = Some(QuestionSetFile {
+                        key,
+                        path: path.to_path_buf(),
+                        set,
+                    });
+                }
+                Err(e) => result.diagnostics.push(Diagnostic::new(
+                    path,
+                    None,
+                    "",
+                    RULE_SCHEMA,
+                    Severity::Error,
+                    e.to_string(),
+                )),
+            }
+        }
+        self.config.lint.apply(&mut result.diagnostics);
+        locate_diagnostics(&mut result.diagnostics, path, &result.source);
+        Ok(result)
+    }
+
+    /// Runs the semantic checks across every file that passed the per-file
+    /// passes.
+    pub fn check_sets(&self, files: &[FileResult]) -> Result<Vec<Diagnostic>> {
+        let loaded: Vec<QuestionSetFile> = files.iter().filter_map(|f| f.set.clone()).collect();
+        let mut semantic = check_question_sets(&loaded);
+        if self.config.lint.check_foodweb {
+            let web = FoodWeb::load(&self.config.paths.foodweb)?;
+            semantic.extend(check_food_web(&loaded, &web));
+        }
+        self.config.lint.apply(&mut semantic);
+        for file in files {
+            locate_diagnostics(&mut semantic, &file.path, &file.source);
+        }
+        Ok(semantic)
+    }
+
+    /// Validates every question file in the data directory.
+    pub fn run(&self) -> Result<Validation> {
+        let files = discover_question_files(&self.config.paths.data_dir)?
+            .iter()
+            .map(|path| self.check_file(path))
+            .collect::<Result<Vec<_>>>()?;
+        let semantic = self.check_sets(&files)?;
+        Ok(Validation::new(files, semantic))
+    }
+}
+
+/// The results of a validation run.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub files: Vec<FileResult>,
+    /// Findings of the semantic checks, which may concern any file.
+    pub semantic: Vec<Diagnostic>,
+    /// Every finding, by file.
+    pub reports: Vec<FileReport>,
+}
+
+impl Validation {
+    pub fn new(files: Vec<FileResult>, semantic: Vec<Diagnostic>) -> Self {
+        let reports = files
+            .iter()
+            .map(|f| FileReport {
+                path: f.path.clone(),
+                diagnostics: f
+                    .diagnostics
+                    .iter()
+                    .chain(semantic.iter().filter(|d| d.file == f.path))
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+        Self {
+            files,
+            semantic,
+            reports,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.reports.iter().all(FileReport::is_valid)
+    }
+
+    /// The source of `file`, for code frames.
+    pub fn source(&self, file: &Path) -> &str {
+        self.files
+            .iter()
+            .find(|f| f.path == file)
+            .map(|f| f.source.as_str())
+            .unwrap_or("")
+    }
+}
@@ -0,0 +1,105 @@
+//! Watching the sources for changes, for `serve` and `validate --watch`.
+//!
+//! The watcher polls: every few hundred milliseconds it compares the
+//! modification time and size of each watched file with the previous scan.
+//! That costs nothing worth measuring for a project of this size and behaves
+//! the same on every platform and editor, including those that save by
+//! writing a new file and renaming it over the old one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// How often the files are scanned.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long the files must stay unchanged before [`Watcher::wait`] reports
+/// them, so a save that touches several files is handled once.
+const QUIET_PERIOD: Duration = Duration::from_millis(300);
+
+/// What a scan records about a file.
+type Stamp = (Option<SystemTime>, u64);
+
+/// Files and directories to watch, and what they looked like when last
+/// scanned.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    snapshot: BTreeMap<PathBuf, Stamp>,
+}
+
+impl Watcher {
+    /// Watches `paths`: files, or directories whose files are watched
+    /// recursively. Paths that don't exist yet are watched for appearing.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let snapshot = scan(&paths);
+        Self { paths, snapshot }
+    }
+
+    /// Replaces the watched paths, e.g. after the configuration changed.
+    /// Differences between the old and new paths are not reported.
+    pub fn set_paths(&mut self, paths: Vec<PathBuf>) {
+        if paths == self.paths {
+            return;
+        }
+        self.snapshot = scan(&paths);
+        self.paths = paths;
+    }
+
+    /// The files added, changed or removed since the last scan.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let snapshot = scan(&self.paths);
+        let mut changed: Vec<PathBuf> = snapshot
+            .iter()
+            .filter(|(path, stamp)| self.snapshot.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(self.snapshot.keys().filter(|path| !snapshot.contains_key(*path)).cloned());
+        changed.sort();
+        self.snapshot = snapshot;
+        changed
+    }
+
+    /// Blocks until files change and then stay unchanged for a moment, and
+    /// returns them.
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        loop {
+            thread::sleep(if changed.is_empty() { POLL_INTERVAL } else { QUIET_PERIOD });
+            let more = self.poll();
+            if more.is_empty() && !changed.is_empty() {
+                return changed;
+            }
+            for path in more {
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+    }
+}
+
+fn scan(paths: &[PathBuf]) -> BTreeMap<PathBuf, Stamp> {
+    let mut snapshot = BTreeMap::new();
+    for path in paths {
+        if path.is_dir() {
+            for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+                if entry.file_type().is_file() {
+                    record(&mut snapshot, entry.path());
+                }
+            }
+        } else {
+            record(&mut snapshot, path);
+        }
+    }
+    snapshot
+}
+
+fn record(snapshot: &mut BTreeMap<PathBuf, Stamp>, path: &Path) {
+    if let Ok(metadata) = fs::metadata(path) {
+        snapshot.insert(path.to_path_buf(), (metadata.modified().ok(), metadata.len()));
+    }
+}